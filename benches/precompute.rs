@@ -114,6 +114,7 @@ fn precompute(bench: &mut Bencher) {
             pdevice,
             queue_family_index,
             None,
+            Default::default(),
         ));
 
         device
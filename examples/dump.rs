@@ -104,6 +104,7 @@ fn main() {
             scattering_mu_size: 64,
             scattering_mu_s_size: 16,
             scattering_nu_size: 4,
+            record_timings: true,
             ..Default::default()
         };
 
@@ -132,6 +133,7 @@ fn main() {
             pdevice,
             queue_family_index,
             None,
+            Default::default(),
         ));
 
         // Precompute look-up tables
@@ -205,6 +207,8 @@ fn main() {
 
         device.device_wait_idle().unwrap();
 
+        eprintln!("{:#?}", pending.timings().unwrap());
+
         write_image(
             "transmittance",
             &*transmittance_buf.ptr,
@@ -0,0 +1,315 @@
+//! Measures the error of each `Quality` level against a much finer reference build
+
+use std::ffi::CStr;
+use std::sync::Arc;
+use std::{mem, slice};
+
+use ash::version::{DeviceV1_0, EntryV1_0, InstanceV1_0};
+use ash::{vk, Device, Entry, Instance};
+use half::f16;
+
+use fuzzyblue::{BuilderOptions, Quality};
+
+const REFERENCE: Quality = Quality {
+    transmittance_samples: 8000,
+    single_scattering_samples: 800,
+    scattering_density_samples: 64,
+    indirect_irradiance_samples: 128,
+    multiple_scattering_samples: 800,
+};
+
+fn main() {
+    unsafe {
+        let entry = Entry::new().unwrap();
+        let app_name = CStr::from_bytes_with_nul(b"fuzzyblue quality\0").unwrap();
+        let instance = entry
+            .create_instance(
+                &vk::InstanceCreateInfo::builder().application_info(
+                    &vk::ApplicationInfo::builder()
+                        .application_name(app_name)
+                        .application_version(0)
+                        .engine_name(app_name)
+                        .engine_version(0)
+                        .api_version(vk::make_version(1, 0, 36)),
+                ),
+                None,
+            )
+            .unwrap();
+
+        let (pdevice, queue_family_index) = instance
+            .enumerate_physical_devices()
+            .unwrap()
+            .iter()
+            .filter_map(|pdevice| {
+                instance
+                    .get_physical_device_queue_family_properties(*pdevice)
+                    .iter()
+                    .position(|info| info.queue_flags.contains(vk::QueueFlags::GRAPHICS))
+                    .map(|index| (*pdevice, index as u32))
+            })
+            .next()
+            .expect("no graphics device available");
+
+        let device = Arc::new(
+            instance
+                .create_device(
+                    pdevice,
+                    &vk::DeviceCreateInfo::builder().queue_create_infos(&[
+                        vk::DeviceQueueCreateInfo::builder()
+                            .queue_family_index(queue_family_index)
+                            .queue_priorities(&[1.0])
+                            .build(),
+                    ]),
+                    None,
+                )
+                .unwrap(),
+        );
+
+        let reference = build(&instance, &device, pdevice, queue_family_index, REFERENCE);
+        for &(name, quality) in &[
+            ("LOW", Quality::LOW),
+            ("MEDIUM", Quality::MEDIUM),
+            ("HIGH", Quality::HIGH),
+        ] {
+            let luts = build(&instance, &device, pdevice, queue_family_index, quality);
+            println!("{}:", name);
+            for (table, (actual, expected)) in ["transmittance", "irradiance", "scattering"]
+                .iter()
+                .zip(luts.iter().zip(&reference))
+            {
+                let (mean, max) = relative_error(actual, expected);
+                println!("  {:13} {:.1e} ({:.1e})", table, mean, max);
+            }
+        }
+
+        device.destroy_device(None);
+        instance.destroy_instance(None);
+    }
+}
+
+/// Transmittance, irradiance, and scattering as built at `quality`
+unsafe fn build(
+    instance: &Instance,
+    device: &Arc<Device>,
+    pdevice: vk::PhysicalDevice,
+    queue_family_index: u32,
+    quality: Quality,
+) -> [Vec<[f32; 4]>; 3] {
+    let memory_props = instance.get_physical_device_memory_properties(pdevice);
+    let queue = device.get_device_queue(queue_family_index, 0);
+    let pool = device
+        .create_command_pool(
+            &vk::CommandPoolCreateInfo::builder().queue_family_index(queue_family_index),
+            None,
+        )
+        .unwrap();
+    let cmd = device
+        .allocate_command_buffers(
+            &vk::CommandBufferAllocateInfo::builder()
+                .command_buffer_count(1)
+                .command_pool(pool)
+                .level(vk::CommandBufferLevel::PRIMARY),
+        )
+        .unwrap()[0];
+
+    let params = fuzzyblue::Parameters {
+        usage: vk::ImageUsageFlags::TRANSFER_SRC,
+        dst_stage_mask: vk::PipelineStageFlags::TRANSFER,
+        dst_access_mask: vk::AccessFlags::TRANSFER_READ,
+        layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        ..Default::default()
+    };
+    let transmittance_extent = vk::Extent3D {
+        width: params.transmittance_extent().width,
+        height: params.transmittance_extent().height,
+        depth: 1,
+    };
+    let irradiance_extent = vk::Extent3D {
+        width: params.irradiance_extent().width,
+        height: params.irradiance_extent().height,
+        depth: 1,
+    };
+    let scattering_extent = params.scattering_extent();
+    let texels = |extent: vk::Extent3D| extent.width * extent.height * extent.depth;
+    let transmittance_buf =
+        Buffer::<[f32; 4]>::new(device, &memory_props, texels(transmittance_extent));
+    let irradiance_buf = Buffer::<[f32; 4]>::new(device, &memory_props, texels(irradiance_extent));
+    let scattering_buf = Buffer::<[f16; 4]>::new(device, &memory_props, texels(scattering_extent));
+
+    let builder = Arc::new(fuzzyblue::Builder::new(
+        instance,
+        device.clone(),
+        vk::PipelineCache::null(),
+        pdevice,
+        queue_family_index,
+        None,
+        BuilderOptions {
+            quality,
+            ..Default::default()
+        },
+    ));
+
+    device
+        .begin_command_buffer(
+            cmd,
+            &vk::CommandBufferBeginInfo::builder()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+        )
+        .unwrap();
+    let pending = fuzzyblue::Atmosphere::build(builder, cmd, &params);
+    let atmosphere = pending.atmosphere();
+    for &(image, buf, extent) in &[
+        (
+            atmosphere.transmittance(),
+            transmittance_buf.handle,
+            transmittance_extent,
+        ),
+        (
+            atmosphere.irradiance(),
+            irradiance_buf.handle,
+            irradiance_extent,
+        ),
+        (
+            atmosphere.scattering(),
+            scattering_buf.handle,
+            scattering_extent,
+        ),
+    ] {
+        device.cmd_copy_image_to_buffer(
+            cmd,
+            image,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            buf,
+            &[vk::BufferImageCopy {
+                buffer_offset: 0,
+                buffer_row_length: 0,
+                buffer_image_height: 0,
+                image_subresource: vk::ImageSubresourceLayers {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    mip_level: 0,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                },
+                image_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+                image_extent: extent,
+            }],
+        );
+    }
+    device.end_command_buffer(cmd).unwrap();
+    device
+        .queue_submit(
+            queue,
+            &[vk::SubmitInfo::builder().command_buffers(&[cmd]).build()],
+            vk::Fence::null(),
+        )
+        .unwrap();
+    device.device_wait_idle().unwrap();
+
+    let luts = [
+        (*transmittance_buf.ptr).to_vec(),
+        (*irradiance_buf.ptr).to_vec(),
+        (*scattering_buf.ptr)
+            .iter()
+            .map(|x| {
+                let mut y = [0.0; 4];
+                for (y, x) in y.iter_mut().zip(x) {
+                    *y = x.to_f32();
+                }
+                y
+            })
+            .collect(),
+    ];
+
+    drop(pending);
+    transmittance_buf.destroy(device);
+    irradiance_buf.destroy(device);
+    scattering_buf.destroy(device);
+    device.destroy_command_pool(pool, None);
+    luts
+}
+
+/// Mean and maximum relative error of the RGB sum of each texel whose reference value is within
+/// a factor of 1000 of the table's largest
+fn relative_error(actual: &[[f32; 4]], expected: &[[f32; 4]]) -> (f64, f64) {
+    let sum = |x: &[f32; 4]| f64::from(x[0] + x[1] + x[2]);
+    let largest = expected.iter().map(sum).fold(0.0, f64::max);
+    let (mut total, mut max, mut count) = (0.0, 0.0f64, 0);
+    for (actual, expected) in actual.iter().zip(expected) {
+        let expected = sum(expected);
+        if expected * 1000.0 < largest {
+            continue;
+        }
+        let error = ((sum(actual) - expected) / expected).abs();
+        total += error;
+        max = max.max(error);
+        count += 1;
+    }
+    (total / f64::from(count.max(1)), max)
+}
+
+fn find_memory_type(
+    device_props: &vk::PhysicalDeviceMemoryProperties,
+    type_bits: u32,
+    flags: vk::MemoryPropertyFlags,
+) -> Option<u32> {
+    (0..device_props.memory_type_count).find(|&i| {
+        type_bits & (1 << i) != 0
+            && device_props.memory_types[i as usize]
+                .property_flags
+                .contains(flags)
+    })
+}
+
+struct Buffer<T: Copy> {
+    handle: vk::Buffer,
+    mem: vk::DeviceMemory,
+    ptr: *mut [T],
+}
+
+impl<T: Copy> Buffer<T> {
+    unsafe fn new(
+        device: &Device,
+        memory_props: &vk::PhysicalDeviceMemoryProperties,
+        pixels: u32,
+    ) -> Self {
+        let bytes = u64::from(pixels) * mem::size_of::<T>() as u64;
+        let handle = device
+            .create_buffer(
+                &vk::BufferCreateInfo {
+                    size: bytes,
+                    usage: vk::BufferUsageFlags::TRANSFER_DST,
+                    ..Default::default()
+                },
+                None,
+            )
+            .unwrap();
+        let reqs = device.get_buffer_memory_requirements(handle);
+        let mem = device
+            .allocate_memory(
+                &vk::MemoryAllocateInfo {
+                    allocation_size: reqs.size,
+                    memory_type_index: find_memory_type(
+                        memory_props,
+                        reqs.memory_type_bits,
+                        vk::MemoryPropertyFlags::HOST_VISIBLE
+                            | vk::MemoryPropertyFlags::HOST_COHERENT,
+                    )
+                    .unwrap(),
+                    ..Default::default()
+                },
+                None,
+            )
+            .unwrap();
+        device.bind_buffer_memory(handle, mem, 0).unwrap();
+        let ptr = device
+            .map_memory(mem, 0, bytes, Default::default())
+            .unwrap() as _;
+        let ptr = slice::from_raw_parts_mut(ptr, pixels as usize);
+        Self { handle, mem, ptr }
+    }
+
+    unsafe fn destroy(&self, device: &Device) {
+        device.destroy_buffer(self.handle, None);
+        device.free_memory(self.mem, None);
+    }
+}
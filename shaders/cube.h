@@ -0,0 +1,30 @@
+// Cube map face addressing
+#ifndef FUZZYBLUE_CUBE_H_
+#define FUZZYBLUE_CUBE_H_
+
+// Unnormalized direction through a point on a cube face, in Vulkan face order
+//
+// `st` ranges from -1 to 1 across the face.
+vec3 CubeFaceDirection(int face, vec2 st) {
+    switch (face) {
+    case 0: return vec3(1, -st.y, -st.x);
+    case 1: return vec3(-1, -st.y, st.x);
+    case 2: return vec3(st.x, 1, st.y);
+    case 3: return vec3(st.x, -1, -st.y);
+    case 4: return vec3(st.x, -st.y, 1);
+    default: return vec3(-st.x, -st.y, -1);
+    }
+}
+
+// Direction through the center of a texel of a cube face with `size` texels on a side
+vec3 CubeTexelDirection(ivec3 texel, int size) {
+    vec2 st = 2 * (vec2(texel.xy) + 0.5) / float(size) - 1;
+    return normalize(CubeFaceDirection(texel.z, st));
+}
+
+// Solid angle subtended by a cube map texel of side `texel_size` centered at `st`
+float CubeTexelSolidAngle(vec2 st, float texel_size) {
+    return texel_size * texel_size / pow(1 + dot(st, st), 1.5);
+}
+
+#endif
@@ -0,0 +1,49 @@
+// Access to the scene depth buffer, either as an input attachment or a sampled image
+#ifndef FUZZYBLUE_DEPTH_H_
+#define FUZZYBLUE_DEPTH_H_
+
+// Reading gl_SampleID causes the shader to be invoked once per sample
+#ifdef MULTISAMPLE
+#define DEPTH_SAMPLE gl_SampleID
+#else
+#define DEPTH_SAMPLE 0
+#endif
+
+#ifdef SAMPLED_DEPTH
+// With multiview, each view is a layer of the depth image
+#if defined(MULTIVIEW) && defined(MULTISAMPLE)
+layout (set=1, binding=0) uniform sampler2DMSArray depth_buffer;
+#elif defined(MULTIVIEW)
+layout (set=1, binding=0) uniform sampler2DArray depth_buffer;
+#elif defined(MULTISAMPLE)
+layout (set=1, binding=0) uniform sampler2DMS depth_buffer;
+#else
+layout (set=1, binding=0) uniform sampler2D depth_buffer;
+#endif
+
+float LoadDepth() {
+#ifdef MULTIVIEW
+    ivec3 coords = ivec3(gl_FragCoord.xy, gl_ViewIndex);
+#else
+    ivec2 coords = ivec2(gl_FragCoord.xy);
+#endif
+    return texelFetch(depth_buffer, coords, DEPTH_SAMPLE).x;
+}
+#else
+// Input attachments are implicitly indexed by view
+#ifdef MULTISAMPLE
+layout (set=1, binding=0, input_attachment_index=0) uniform subpassInputMS depth_buffer;
+
+float LoadDepth() {
+    return subpassLoad(depth_buffer, gl_SampleID).x;
+}
+#else
+layout (set=1, binding=0, input_attachment_index=0) uniform subpassInput depth_buffer;
+
+float LoadDepth() {
+    return subpassLoad(depth_buffer).x;
+}
+#endif
+#endif
+
+#endif
@@ -0,0 +1,69 @@
+// Per-frame inputs shared by the screen-space passes
+#ifndef FUZZYBLUE_DRAW_PARAMS_H_
+#define FUZZYBLUE_DRAW_PARAMS_H_
+
+#ifdef MULTIVIEW
+#extension GL_EXT_multiview : require
+
+// Must match MAX_VIEWS in render.rs
+#define MAX_VIEWS 32
+
+struct View {
+    mat4 inverse_viewproj;
+    vec3 camera_position;
+};
+
+layout (set=1, binding=2) uniform Views {
+    View views[MAX_VIEWS];
+};
+
+#define inverse_viewproj (views[gl_ViewIndex].inverse_viewproj)
+#define camera_position (views[gl_ViewIndex].camera_position)
+
+// The per-view parameters are pushed but ignored
+layout (push_constant) uniform DrawParams {
+    layout (offset=76) float background_rotation;
+    vec3 sun_direction;
+    // Depth buffer value at the far plane: 1 conventionally, 0 for reverse-Z
+    float far_depth;
+    vec3 solar_irradiance;
+    // Weight of the atmosphere in set 2, crossfading from the one in set 0
+    float blend;
+};
+#else
+layout (push_constant) uniform DrawParams {
+    mat4 inverse_viewproj;
+    vec3 camera_position;
+    float background_rotation;
+    vec3 sun_direction;
+    // Depth buffer value at the far plane: 1 conventionally, 0 for reverse-Z
+    float far_depth;
+    vec3 solar_irradiance;
+    // Weight of the atmosphere in set 2, crossfading from the one in set 0
+    float blend;
+};
+#endif
+
+// Whether nothing was drawn at a pixel with the given depth
+bool IsSky(float depth) {
+    return depth == far_depth;
+}
+
+vec3 GetViewRay(vec2 screen_coords) {
+    // Unproject two points that are finite for any projection, even with an infinite far plane,
+    // and take the difference in homogeneous coordinates so as not to assume anything about w.
+    vec2 ndc = 2*screen_coords - 1;
+    float near_depth = 1 - far_depth;
+    vec4 near_point = inverse_viewproj * vec4(ndc, near_depth, 1);
+    vec4 mid_point = inverse_viewproj * vec4(ndc, 0.5, 1);
+    return normalize(mid_point.xyz * near_point.w - near_point.xyz * mid_point.w);
+}
+
+// Rotate a direction from the planet's frame into the background's celestial frame
+vec3 ToCelestial(vec3 v) {
+    float c = cos(background_rotation);
+    float s = sin(background_rotation);
+    return vec3(c * v.x - s * v.y, s * v.x + c * v.y, v.z);
+}
+
+#endif
@@ -37,4 +37,32 @@ vec3 GetIrradiance(
     return texture(irradiance_texture, uv).rgb;
 }
 
+// Sky irradiance on a surface at radius r with unit normal `normal`, expressed relative to the local
+// vertical `up` and the unit horizontal vector `sunward` towards the sun's azimuth
+vec3 GetDirectionalIrradiance(
+    AtmosphereParameters atmosphere,
+    sampler2DArray directional_irradiance_texture,
+    float r, float mu_s, float normal_up, float normal_sunward) {
+    vec2 uv = GetIrradianceTextureUvFromRMuS(atmosphere, r, mu_s);
+    vec3 isotropic = texture(directional_irradiance_texture, vec3(uv, 0)).rgb;
+    vec3 vertical = texture(directional_irradiance_texture, vec3(uv, 1)).rgb;
+    vec3 sunward = texture(directional_irradiance_texture, vec3(uv, 2)).rgb;
+    // The truncated expansion can ring slightly negative facing away from a bright sky
+    return max(isotropic + vertical * normal_up + sunward * normal_sunward, vec3(0.0));
+}
+
+// As `GetDirectionalIrradiance`, but exact for an up-facing normal, with the expansion only
+// accounting for the surface's tilt away from horizontal
+vec3 GetTiltedIrradiance(
+    AtmosphereParameters atmosphere,
+    sampler2D irradiance_texture,
+    sampler2DArray directional_irradiance_texture,
+    float r, float mu_s, float normal_up, float normal_sunward) {
+    vec2 uv = GetIrradianceTextureUvFromRMuS(atmosphere, r, mu_s);
+    vec3 horizontal = texture(irradiance_texture, uv).rgb;
+    vec3 vertical = texture(directional_irradiance_texture, vec3(uv, 1)).rgb;
+    vec3 sunward = texture(directional_irradiance_texture, vec3(uv, 2)).rgb;
+    return max(horizontal + vertical * (normal_up - 1.0) + sunward * normal_sunward, vec3(0.0));
+}
+
 #endif
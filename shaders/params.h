@@ -24,8 +24,11 @@ struct DensityProfile {
 
 // Fields ordered for density
 struct AtmosphereParameters {
-    // The solar irradiance at the top of the atmosphere.
-    vec3 solar_irradiance;
+    // The extinction coefficient of molecules that absorb light (e.g. ozone) at
+    // the altitude where their density is maximum, as a function of wavelength.
+    // The extinction coefficient at altitude h is equal to
+    // 'absorption_extinction' times 'absorption_density' at this altitude.
+    vec3 absorption_extinction;
     // The sun's angular radius. Warning: the implementation uses approximations
     // that are valid only if this angle is smaller than 0.1 radians.
     float sun_angular_radius;
@@ -58,11 +61,6 @@ struct AtmosphereParameters {
     // angle yielding negligible sky light radiance values. For instance, for the
     // Earth case, 102 degrees is a good choice - yielding mu_s_min = -0.2).
     float mu_s_min;
-    // The extinction coefficient of molecules that absorb light (e.g. ozone) at
-    // the altitude where their density is maximum, as a function of wavelength.
-    // The extinction coefficient at altitude h is equal to
-    // 'absorption_extinction' times 'absorption_density' at this altitude.
-    vec3 absorption_extinction;
 
     // Texture size parameters
     int transmittance_texture_mu_size;
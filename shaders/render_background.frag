@@ -0,0 +1,23 @@
+#version 450
+
+#include "draw_params.h"
+#include "depth.h"
+
+#ifdef MULTISAMPLE
+// Interpolated at each sample's position to match its depth
+layout (location=0) sample in vec2 screen_coords;
+#else
+layout (location=0) in vec2 screen_coords;
+#endif
+
+layout (location=0) out vec4 color_out;
+
+layout (set=1, binding=1) uniform samplerCube background;
+
+void main() {
+    // Only visible where nothing else was drawn
+    if (!IsSky(LoadDepth())) {
+        discard;
+    }
+    color_out = vec4(texture(background, ToCelestial(GetViewRay(screen_coords))).rgb, 0);
+}
@@ -6,22 +6,30 @@
 #include "transmittance.h"
 #include "irradiance.h"
 
-// Returns direct illumination, last argument outputs indirect illumination
+// Returns direct illumination, last argument outputs indirect illumination. `solar_irradiance`
+// should match `DrawParameters::solar_irradiance`.
 vec3 GetSunAndSkyIrradiance(
     AtmosphereParameters atmosphere,
     sampler2D transmittance_texture,
     sampler2D irradiance_texture,
-    vec3 point, vec3 normal, vec3 sun_direction,
+    sampler2DArray directional_irradiance_texture,
+    vec3 point, vec3 normal, vec3 sun_direction, vec3 solar_irradiance,
     out vec3 sky_irradiance) {
     float r = length(point);
-    float mu_s = dot(point, sun_direction) / r;
+    vec3 up = point / r;
+    float mu_s = dot(up, sun_direction);
 
-    // Indirect irradiance (approximated if the surface is not horizontal).
-    sky_irradiance = GetIrradiance(atmosphere, irradiance_texture, r, mu_s) *
-        (1.0 + dot(normal, point) / r) * 0.5;
+    // Indirect irradiance. The sun's azimuth is undefined when it's directly overhead or underfoot,
+    // but the sky is then symmetric about the vertical and any horizontal direction will do.
+    vec3 sunward = sun_direction - up * mu_s;
+    float sunward_length = length(sunward);
+    float normal_sunward = sunward_length > 1e-4 ? dot(normal, sunward) / sunward_length : 0.0;
+    sky_irradiance = solar_irradiance * GetTiltedIrradiance(
+        atmosphere, irradiance_texture, directional_irradiance_texture,
+        r, mu_s, dot(normal, up), normal_sunward);
 
     // Direct irradiance.
-    return atmosphere.solar_irradiance *
+    return solar_irradiance *
         GetTransmittanceToSun(
             atmosphere, transmittance_texture, r, mu_s) *
         max(dot(normal, sun_direction), 0.0);
@@ -0,0 +1,25 @@
+#version 450
+
+// Exercises render_lighting.h, which no built-in shader uses, so that breaking it fails the build
+
+#include "render_lighting.h"
+
+layout (set=0, binding=0) uniform Params {
+    AtmosphereParameters atmosphere;
+};
+layout (set=0, binding=1) uniform sampler2D transmittance_texture;
+layout (set=0, binding=2) uniform sampler2D irradiance_texture;
+layout (set=0, binding=3) uniform sampler2DArray directional_irradiance_texture;
+
+layout (location=0) in vec3 point;
+layout (location=1) in vec3 normal;
+layout (location=2) in vec3 sun_direction;
+layout (location=0) out vec4 color_out;
+
+void main() {
+    vec3 sky_irradiance;
+    vec3 sun_irradiance = GetSunAndSkyIrradiance(
+        atmosphere, transmittance_texture, irradiance_texture, directional_irradiance_texture,
+        point, normal, sun_direction, vec3(1.0), sky_irradiance);
+    color_out = vec4(sun_irradiance + sky_irradiance, 1);
+}
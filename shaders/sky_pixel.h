@@ -0,0 +1,62 @@
+// Atmospheric scattering between the camera and the scene at a single pixel
+#ifndef FUZZYBLUE_SKY_PIXEL_H_
+#define FUZZYBLUE_SKY_PIXEL_H_
+
+// First, as it may enable extensions
+#include "draw_params.h"
+#include "params.h"
+#include "render_sky.h"
+
+layout (set=0, binding=0) uniform Params {
+    AtmosphereParameters atmosphere;
+};
+layout (set=0, binding=1) uniform sampler2D transmittance_texture;
+layout (set=0, binding=2) uniform sampler3D scattering_texture;
+
+// The atmosphere being blended towards
+layout (set=2, binding=0) uniform BlendParams {
+    AtmosphereParameters blend_atmosphere;
+};
+layout (set=2, binding=1) uniform sampler2D blend_transmittance_texture;
+layout (set=2, binding=2) uniform sampler3D blend_scattering_texture;
+
+vec3 GetPixelRadiance(
+    AtmosphereParameters atmosphere,
+    sampler2D transmittance_texture,
+    sampler3D scattering_texture,
+    vec2 screen_coords, float depth, out vec3 transmittance) {
+    vec3 view = GetViewRay(screen_coords);
+    if (IsSky(depth)) {
+        // The far plane may be at infinity, so don't try to unproject it
+        return GetSkyRadiance(
+            atmosphere, transmittance_texture, scattering_texture,
+            camera_position, view, sun_direction,
+            transmittance);
+    }
+    vec4 world_pre = (inverse_viewproj * vec4(2*screen_coords - 1, depth, 1));
+    vec3 world = (world_pre.xyz / world_pre.w) * 1e-3;
+    return GetSkyRadianceToPoint(
+        atmosphere, transmittance_texture, scattering_texture,
+        camera_position, view, world, sun_direction,
+        transmittance);
+}
+
+// Light scattered towards the camera in front of the scene at `depth`, and the transmittance of
+// the scene's own light
+vec3 GetPixelRadiance(vec2 screen_coords, float depth, out vec3 transmittance) {
+    vec3 radiance = GetPixelRadiance(
+        atmosphere, transmittance_texture, scattering_texture,
+        screen_coords, depth, transmittance);
+    if (blend > 0) {
+        vec3 blend_transmittance;
+        vec3 blend_radiance = GetPixelRadiance(
+            blend_atmosphere, blend_transmittance_texture, blend_scattering_texture,
+            screen_coords, depth, blend_transmittance);
+        radiance = mix(radiance, blend_radiance, blend);
+        transmittance = mix(transmittance, blend_transmittance, blend);
+    }
+    // The look-up tables are computed for unit solar irradiance
+    return radiance * solar_irradiance;
+}
+
+#endif
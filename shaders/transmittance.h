@@ -23,6 +23,24 @@ vec2 GetTransmittanceTextureUvFromRMu(AtmosphereParameters atmosphere, float r,
                 GetTextureCoordFromUnitRange(x_r, atmosphere.transmittance_texture_r_size));
 }
 
+vec2 GetRMuFromUnitRanges(AtmosphereParameters atmosphere, float x_mu, float x_r) {
+    // Distance to top atmosphere boundary for a horizontal ray at ground level.
+    float H = sqrt(atmosphere.top_radius * atmosphere.top_radius -
+                   atmosphere.bottom_radius * atmosphere.bottom_radius);
+    // Distance to the horizon, from which we can compute r:
+    float rho = H * x_r;
+    float r = sqrt(rho * rho + atmosphere.bottom_radius * atmosphere.bottom_radius);
+    // Distance to the top atmosphere boundary for the ray (r,mu), and its minimum
+    // and maximum values over all mu - obtained for (r,1) and (r,mu_horizon) -
+    // from which we can recover mu:
+    float d_min = atmosphere.top_radius - r;
+    float d_max = rho + H;
+    float d = d_min + x_mu * (d_max - d_min);
+    float mu = d == 0.0 ? 1.0 : (H * H - rho * rho - d * d) / (2.0 * r * d);
+    mu = ClampCosine(mu);
+    return vec2(r, mu);
+}
+
 vec3 GetTransmittanceToTopAtmosphereBoundary(
     AtmosphereParameters atmosphere,
     sampler2D transmittance_texture,
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use ash::version::DeviceV1_0;
+use ash::{vk, Device};
+
+use crate::precompute::allocate;
+
+/// Source of device memory for resources owned by a `Builder` and the `Atmosphere`s it builds
+///
+/// Allows suballocation from larger blocks and integration with an engine's budget tracking.
+pub trait Allocator: Send + Sync {
+    /// Allocate memory satisfying `reqs` from a memory type with at least `flags`
+    ///
+    /// `Allocation::offset` must be a multiple of `reqs.alignment`. Host-visible allocations are
+    /// mapped with `vkMapMemory` while results are read back, so their `memory` must not be mapped
+    /// elsewhere at that time.
+    unsafe fn allocate(
+        &self,
+        reqs: vk::MemoryRequirements,
+        flags: vk::MemoryPropertyFlags,
+    ) -> Allocation;
+
+    /// Release memory returned by `allocate` once the device is no longer using it
+    unsafe fn free(&self, allocation: Allocation);
+}
+
+/// A range of device memory returned by an `Allocator`
+#[derive(Debug, Copy, Clone)]
+pub struct Allocation {
+    pub memory: vk::DeviceMemory,
+    pub offset: vk::DeviceSize,
+}
+
+/// Dedicates a `vkAllocateMemory` call to every allocation
+pub struct DefaultAllocator {
+    device: Arc<Device>,
+    memory_props: vk::PhysicalDeviceMemoryProperties,
+}
+
+impl DefaultAllocator {
+    pub fn new(device: Arc<Device>, memory_props: vk::PhysicalDeviceMemoryProperties) -> Self {
+        Self {
+            device,
+            memory_props,
+        }
+    }
+}
+
+impl Allocator for DefaultAllocator {
+    unsafe fn allocate(
+        &self,
+        reqs: vk::MemoryRequirements,
+        flags: vk::MemoryPropertyFlags,
+    ) -> Allocation {
+        Allocation {
+            memory: allocate(&self.device, &self.memory_props, reqs, flags).unwrap(),
+            offset: 0,
+        }
+    }
+
+    unsafe fn free(&self, allocation: Allocation) {
+        self.device.free_memory(allocation.memory, None);
+    }
+}
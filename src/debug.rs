@@ -0,0 +1,72 @@
+use std::ffi::{CStr, CString};
+use std::os::raw::c_void;
+use std::ptr;
+
+use ash::version::InstanceV1_0;
+use ash::vk::{self, Handle};
+use ash::{Device, Instance};
+
+/// Commands called through `DebugUtils`, which must all be available
+const USED: [&[u8]; 3] = [
+    b"vkSetDebugUtilsObjectNameEXT",
+    b"vkCmdBeginDebugUtilsLabelEXT",
+    b"vkCmdEndDebugUtilsLabelEXT",
+];
+
+/// `VK_EXT_debug_utils` commands, for naming objects and labeling commands in tools like RenderDoc
+#[derive(Clone)]
+pub(crate) struct DebugUtils {
+    device: vk::Device,
+    fp: vk::ExtDebugUtilsFn,
+}
+
+impl DebugUtils {
+    /// Load the extension's commands, if `instance` has it enabled
+    pub(crate) fn new(instance: &Instance, device: &Device) -> Option<Self> {
+        let mut available = true;
+        let fp = vk::ExtDebugUtilsFn::load(|name: &CStr| unsafe {
+            let f = instance
+                .get_device_proc_addr(device.handle(), name.as_ptr())
+                .map_or(ptr::null(), |f| f as *const c_void);
+            if f.is_null() && USED.contains(&name.to_bytes()) {
+                available = false;
+            }
+            f
+        });
+        if !available {
+            return None;
+        }
+        Some(Self {
+            device: device.handle(),
+            fp,
+        })
+    }
+
+    pub(crate) unsafe fn name<T: Handle>(&self, object: T, name: &str) {
+        let name = CString::new(name).unwrap();
+        self.fp.set_debug_utils_object_name_ext(
+            self.device,
+            &vk::DebugUtilsObjectNameInfoEXT {
+                object_type: T::TYPE,
+                object_handle: object.as_raw(),
+                p_object_name: name.as_ptr(),
+                ..Default::default()
+            },
+        );
+    }
+
+    pub(crate) unsafe fn begin_label(&self, cmd: vk::CommandBuffer, name: &str) {
+        let name = CString::new(name).unwrap();
+        self.fp.cmd_begin_debug_utils_label_ext(
+            cmd,
+            &vk::DebugUtilsLabelEXT {
+                p_label_name: name.as_ptr(),
+                ..Default::default()
+            },
+        );
+    }
+
+    pub(crate) unsafe fn end_label(&self, cmd: vk::CommandBuffer) {
+        self.fp.cmd_end_debug_utils_label_ext(cmd);
+    }
+}
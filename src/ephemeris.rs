@@ -0,0 +1,205 @@
+//! Approximate positions of the sun and moon as seen from Earth
+//!
+//! Based on the low-precision formulae of the Astronomical Almanac, which are accurate to within a
+//! few hundredths of a degree for the sun and a few tenths of a degree for the moon over several
+//! centuries around the year 2000. Much better than that is rarely visible in a sky.
+//!
+//! Results are expressed in the planet's reference frame, as used by `DrawParameters`, taken to be
+//! Earth-fixed: +Z points through the north pole, +X through the intersection of the equator and
+//! the prime meridian, and +Y through the equator at 90° east. Distances are in km.
+
+use std::f64::consts::PI;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Direction from the planet's center towards the sun at `time`
+pub fn sun_direction(time: SystemTime) -> [f32; 3] {
+    let d = days_since_j2000(time);
+    to_f32(equatorial_to_planet(d, sun_equatorial(d)))
+}
+
+/// Position and phase of the moon at `time` as seen from `observer`
+///
+/// `observer` is a position in the planet's reference frame, e.g.
+/// `DrawParameters::camera_position`. The moon is close enough that the observer's location shifts
+/// its apparent position by up to about a degree.
+pub fn moon(time: SystemTime, observer: [f32; 3]) -> Moon {
+    let d = days_since_j2000(time);
+    let t = d / 36525.0;
+
+    let longitude = 218.32 + 481_267.881 * t + 6.29 * sin_deg(135.0 + 477_198.87 * t)
+        - 1.27 * sin_deg(259.3 - 413_335.36 * t)
+        + 0.66 * sin_deg(235.7 + 890_534.22 * t)
+        + 0.21 * sin_deg(269.9 + 954_397.74 * t)
+        - 0.19 * sin_deg(357.5 + 35_999.05 * t)
+        - 0.11 * sin_deg(186.5 + 966_404.03 * t);
+    let latitude = 5.13 * sin_deg(93.3 + 483_202.02 * t) + 0.28 * sin_deg(228.2 + 960_400.89 * t)
+        - 0.28 * sin_deg(318.3 + 6_003.15 * t)
+        - 0.17 * sin_deg(217.6 - 407_332.21 * t);
+    let parallax = 0.9508
+        + 0.0518 * cos_deg(135.0 + 477_198.87 * t)
+        + 0.0095 * cos_deg(259.3 - 413_335.36 * t)
+        + 0.0078 * cos_deg(235.7 + 890_534.22 * t)
+        + 0.0028 * cos_deg(269.9 + 954_397.74 * t);
+    let geocentric_distance = EARTH_EQUATORIAL_RADIUS / sin_deg(parallax);
+
+    let moon_dir = equatorial_to_planet(d, ecliptic_to_equatorial(d, longitude, latitude));
+    let sun_dir = equatorial_to_planet(d, sun_equatorial(d));
+
+    // Topocentric position
+    let position = [
+        moon_dir[0] * geocentric_distance - f64::from(observer[0]),
+        moon_dir[1] * geocentric_distance - f64::from(observer[1]),
+        moon_dir[2] * geocentric_distance - f64::from(observer[2]),
+    ];
+    let distance = dot(position, position).sqrt();
+    let direction = [
+        position[0] / distance,
+        position[1] / distance,
+        position[2] / distance,
+    ];
+
+    // Angle at the moon between the sun and the observer. The sun is far enough away that its
+    // direction is effectively the same from the moon as from the observer.
+    let elongation = dot(direction, sun_dir).clamp(-1.0, 1.0).acos();
+    let sun_distance = sun_distance(d);
+    let phase_angle =
+        (sun_distance * elongation.sin()).atan2(distance - sun_distance * elongation.cos());
+
+    Moon {
+        direction: to_f32(direction),
+        distance: distance as f32,
+        angular_radius: (MOON_RADIUS / distance).asin() as f32,
+        illuminated_fraction: ((1.0 + phase_angle.cos()) / 2.0) as f32,
+        phase_angle: phase_angle as f32,
+    }
+}
+
+/// Rotation about +Z from the planet's reference frame to the equatorial frame at `time`
+///
+/// Suitable for `DrawParameters::background_rotation` with a background in J2000 equatorial
+/// coordinates, such as one produced by `stars::rasterize_cubemap`.
+pub fn sidereal_angle(time: SystemTime) -> f32 {
+    greenwich_mean_sidereal_angle(days_since_j2000(time)) as f32
+}
+
+/// The moon's apparent position and phase
+#[derive(Debug, Copy, Clone)]
+pub struct Moon {
+    /// Unit vector from the observer towards the moon's center
+    pub direction: [f32; 3],
+    /// Distance from the observer to the moon's center
+    pub distance: f32,
+    /// Apparent angular radius of the moon's disk, in radians
+    pub angular_radius: f32,
+    /// Fraction of the visible disk that is lit, from 0 (new moon) to 1 (full moon)
+    pub illuminated_fraction: f32,
+    /// Angle at the moon between the directions to the sun and to the observer, in radians
+    ///
+    /// 0 at full moon, π at new moon. Lunar brightness falls off much faster than
+    /// `illuminated_fraction` as this grows, so it's the better input for a photometric model.
+    pub phase_angle: f32,
+}
+
+/// Geocentric unit vector towards the sun in the equatorial frame
+fn sun_equatorial(d: f64) -> [f64; 3] {
+    let mean_longitude = 280.460 + 0.985_647_4 * d;
+    let mean_anomaly = 357.528 + 0.985_600_3 * d;
+    let longitude =
+        mean_longitude + 1.915 * sin_deg(mean_anomaly) + 0.020 * sin_deg(2.0 * mean_anomaly);
+    ecliptic_to_equatorial(d, longitude, 0.0)
+}
+
+/// Distance from the planet's center to the sun, in km
+fn sun_distance(d: f64) -> f64 {
+    let mean_anomaly = 357.528 + 0.985_600_3 * d;
+    let au = 1.000_14 - 0.016_71 * cos_deg(mean_anomaly) - 0.000_14 * cos_deg(2.0 * mean_anomaly);
+    au * ASTRONOMICAL_UNIT
+}
+
+/// Unit vector in the equatorial frame from ecliptic longitude and latitude in degrees
+fn ecliptic_to_equatorial(d: f64, longitude: f64, latitude: f64) -> [f64; 3] {
+    let obliquity = (23.439 - 0.000_000_4 * d).to_radians();
+    let (longitude, latitude) = (longitude.to_radians(), latitude.to_radians());
+    let ecliptic = [
+        latitude.cos() * longitude.cos(),
+        latitude.cos() * longitude.sin(),
+        latitude.sin(),
+    ];
+    [
+        ecliptic[0],
+        obliquity.cos() * ecliptic[1] - obliquity.sin() * ecliptic[2],
+        obliquity.sin() * ecliptic[1] + obliquity.cos() * ecliptic[2],
+    ]
+}
+
+/// Rotate from the equatorial frame into the planet's rotating frame
+fn equatorial_to_planet(d: f64, v: [f64; 3]) -> [f64; 3] {
+    let (sin, cos) = greenwich_mean_sidereal_angle(d).sin_cos();
+    [cos * v[0] + sin * v[1], -sin * v[0] + cos * v[1], v[2]]
+}
+
+/// Angle between the vernal equinox and the prime meridian, in radians
+fn greenwich_mean_sidereal_angle(d: f64) -> f64 {
+    (280.460_618_37 + 360.985_647_366_29 * d).rem_euclid(360.0) * PI / 180.0
+}
+
+/// Fractional days since 2000-01-01 12:00 UTC
+fn days_since_j2000(time: SystemTime) -> f64 {
+    let unix_seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(x) => x.as_secs_f64(),
+        Err(e) => -e.duration().as_secs_f64(),
+    };
+    (unix_seconds - J2000_UNIX_SECONDS) / 86_400.0
+}
+
+fn sin_deg(x: f64) -> f64 {
+    x.to_radians().sin()
+}
+
+fn cos_deg(x: f64) -> f64 {
+    x.to_radians().cos()
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn to_f32(v: [f64; 3]) -> [f32; 3] {
+    [v[0] as f32, v[1] as f32, v[2] as f32]
+}
+
+const J2000_UNIX_SECONDS: f64 = 946_728_000.0;
+const EARTH_EQUATORIAL_RADIUS: f64 = 6378.14;
+const MOON_RADIUS: f64 = 1737.4;
+const ASTRONOMICAL_UNIT: f64 = 149_597_870.7;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn utc(unix_seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(unix_seconds)
+    }
+
+    #[test]
+    fn full_moon() {
+        // 2000-01-21 04:40 UTC, during a total lunar eclipse
+        let moon = moon(utc(948_429_600), [0.0; 3]);
+        assert!(moon.illuminated_fraction > 0.99);
+    }
+
+    #[test]
+    fn new_moon() {
+        // 2000-02-05 13:03 UTC
+        let moon = moon(utc(949_755_780), [0.0; 3]);
+        assert!(moon.illuminated_fraction < 0.01);
+    }
+
+    #[test]
+    fn equinox() {
+        // 2000-03-20 07:35 UTC
+        let sun = sun_direction(utc(953_537_700));
+        assert!(sun[2].abs() < 1e-3);
+    }
+}
@@ -5,8 +5,31 @@
 
 #![allow(clippy::missing_safety_doc)]
 
+mod allocator;
+mod debug;
+pub use allocator::{Allocation, Allocator, DefaultAllocator};
+
+pub mod ephemeris;
+
+mod manager;
+pub use manager::AtmosphereManager;
+
 mod precompute;
-pub use precompute::{Atmosphere, Builder, Parameters, PendingAtmosphere};
+pub use precompute::{
+    Atmosphere, Builder, BuilderOptions, IncrementalAtmosphere, LutReport, MemoryReport,
+    OrderExport, OrderTimings, Parameters, PendingAtmosphere, PrecomputeShaders, PrecomputeTimings,
+    Quality, TableReport, Texel, WorkgroupSizes,
+};
+
+mod probe;
+pub use probe::{Probe, ProbeParameters, ProbeRenderer};
 
 mod render;
-pub use render::{DrawParameters, Renderer};
+pub use render::{
+    ComputeRenderShaders, ComputeRenderer, DepthConvention, DrawParameters, RenderShaders,
+    Renderer, RendererOptions, ViewParameters,
+};
+
+mod shader;
+
+pub mod stars;
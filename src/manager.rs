@@ -0,0 +1,167 @@
+use std::{collections::VecDeque, mem, sync::Arc};
+
+use ash::version::DeviceV1_0;
+use ash::{vk, Device};
+
+use crate::{Atmosphere, Builder, Parameters, PendingAtmosphere};
+
+/// Owns the current `Atmosphere` and replaces it with new ones built in the background
+///
+/// Precompute is submitted to `Builder`'s compute queue family if it has one, or to the graphics
+/// queue family otherwise, and tracked with a fence. Must not be dropped while the current or any
+/// recently replaced atmosphere may be in use by the GPU.
+pub struct AtmosphereManager {
+    builder: Arc<Builder>,
+    device: Arc<Device>,
+    queue: vk::Queue,
+    command_pool: vk::CommandPool,
+    cmd: vk::CommandBuffer,
+    fence: vk::Fence,
+    frames: usize,
+    current: Atmosphere,
+    pending: Option<PendingAtmosphere>,
+    /// Replaced atmospheres, and the number of calls to `poll` remaining before each is destroyed
+    retired: VecDeque<(usize, Atmosphere)>,
+}
+
+impl Drop for AtmosphereManager {
+    fn drop(&mut self) {
+        unsafe {
+            if self.pending.is_some() {
+                self.device
+                    .wait_for_fences(&[self.fence], true, u64::MAX)
+                    .unwrap();
+                self.pending = None;
+            }
+            self.device.destroy_fence(self.fence, None);
+            self.device.destroy_command_pool(self.command_pool, None);
+        }
+    }
+}
+
+impl AtmosphereManager {
+    /// Manage `atmosphere`, rebuilding it on `queue`
+    ///
+    /// `queue` must belong to `builder`'s compute queue family, or its graphics queue family if it
+    /// has none, and must not be used concurrently with calls to `update`. `frames` is the maximum
+    /// number of frames that may be in flight at once.
+    pub fn new(
+        builder: Arc<Builder>,
+        queue: vk::Queue,
+        atmosphere: Atmosphere,
+        frames: usize,
+    ) -> Self {
+        let device = builder.device().clone();
+        let queue_family = builder
+            .compute_queue_family()
+            .unwrap_or_else(|| builder.gfx_queue_family());
+        unsafe {
+            let command_pool = device
+                .create_command_pool(
+                    &vk::CommandPoolCreateInfo::builder()
+                        .queue_family_index(queue_family)
+                        .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER),
+                    None,
+                )
+                .unwrap();
+            let cmd = device
+                .allocate_command_buffers(
+                    &vk::CommandBufferAllocateInfo::builder()
+                        .command_pool(command_pool)
+                        .command_buffer_count(1),
+                )
+                .unwrap()[0];
+            let fence = device
+                .create_fence(&vk::FenceCreateInfo::default(), None)
+                .unwrap();
+            Self {
+                builder,
+                device,
+                queue,
+                command_pool,
+                cmd,
+                fence,
+                frames,
+                current: atmosphere,
+                pending: None,
+                retired: VecDeque::new(),
+            }
+        }
+    }
+
+    /// The atmosphere to draw this frame
+    pub fn current(&self) -> &Atmosphere {
+        &self.current
+    }
+
+    /// Whether an atmosphere is being built that hasn't yet been swapped in by `poll`
+    pub fn is_updating(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Begin building a new atmosphere from `params`
+    ///
+    /// Returns `false` without doing anything if a previous update is still in progress.
+    /// `params.dst_stage_mask`, `dst_access_mask`, and `layout` should describe use by the
+    /// renderer.
+    pub fn update(&mut self, params: &Parameters) -> bool {
+        if self.pending.is_some() {
+            return false;
+        }
+        unsafe {
+            self.device
+                .begin_command_buffer(
+                    self.cmd,
+                    &vk::CommandBufferBeginInfo::builder()
+                        .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+                )
+                .unwrap();
+            let pending = Atmosphere::build(self.builder.clone(), self.cmd, params);
+            self.device.end_command_buffer(self.cmd).unwrap();
+            let cmds = [self.cmd];
+            self.device
+                .queue_submit(
+                    self.queue,
+                    &[vk::SubmitInfo::builder().command_buffers(&cmds).build()],
+                    self.fence,
+                )
+                .unwrap();
+            self.pending = Some(pending);
+        }
+        true
+    }
+
+    /// Swap in a newly built atmosphere if one is ready, and destroy those no longer in use
+    ///
+    /// Call once per frame, after waiting for the GPU to finish the frame that last used the same
+    /// resources, with the command buffer that will draw `current()` for this frame, on the
+    /// graphics queue family. If the atmosphere was built on a different queue family, a barrier
+    /// acquiring ownership of it is recorded into `cmd`, so `cmd` must be recording and must
+    /// execute before any other use of the new atmosphere. Returns whether `current()` changed.
+    pub fn poll(&mut self, cmd: vk::CommandBuffer) -> bool {
+        for (remaining, _) in &mut self.retired {
+            *remaining = remaining.saturating_sub(1);
+        }
+        while let Some(&(0, _)) = self.retired.front() {
+            self.retired.pop_front();
+        }
+
+        unsafe {
+            if self.pending.is_none() || !self.device.get_fence_status(self.fence).unwrap() {
+                return false;
+            }
+            self.device.reset_fences(&[self.fence]).unwrap();
+            let pending = self.pending.take().unwrap();
+            let gfx_queue_family = self.builder.gfx_queue_family();
+            match self.builder.compute_queue_family() {
+                Some(compute_queue_family) if compute_queue_family != gfx_queue_family => {
+                    pending.acquire_ownership(cmd, compute_queue_family, gfx_queue_family);
+                }
+                _ => {}
+            }
+            let old = mem::replace(&mut self.current, pending.assert_ready());
+            self.retired.push_back((self.frames, old));
+        }
+        true
+    }
+}
@@ -1,32 +1,47 @@
-use std::{mem, ptr, sync::Arc};
+use std::{mem, ptr, sync::Arc, time::Duration};
 
 use ash::version::{DeviceV1_0, InstanceV1_0};
-use ash::{vk, Device, Instance};
+use ash::vk::{self, Handle};
+use ash::{Device, Instance};
 use vk_shader_macros::include_glsl;
 
+use crate::debug::DebugUtils;
+use crate::shader::{self, Interface};
+use crate::{Allocation, Allocator, DefaultAllocator};
+
 const TRANSMITTANCE: &[u32] = include_glsl!("shaders/transmittance.comp");
 const SINGLE_SCATTERING: &[u32] = include_glsl!("shaders/single_scattering.comp");
 const SCATTERING_DENSITY: &[u32] = include_glsl!("shaders/scattering_density.comp");
 const MULTIPLE_SCATTERING: &[u32] = include_glsl!("shaders/multiple_scattering.comp");
 const DIRECT_IRRADIANCE: &[u32] = include_glsl!("shaders/direct_irradiance.comp");
 const INDIRECT_IRRADIANCE: &[u32] = include_glsl!("shaders/indirect_irradiance.comp");
+const CONVERGENCE: &[u32] = include_glsl!("shaders/convergence.comp");
+const VALIDATE_LUTS: &[u32] = include_glsl!("shaders/validate_luts.comp");
 
 /// Constructs `Atmosphere`s
 pub struct Builder {
     device: Arc<Device>,
     memory_props: vk::PhysicalDeviceMemoryProperties,
+    allocator: Arc<dyn Allocator>,
     gfx_queue_family: u32,
     compute_queue_family: Option<u32>,
+    /// Nanoseconds per timestamp tick
+    timestamp_period: f32,
+    /// Valid bits of timestamps written by the precompute queue family
+    timestamp_mask: u64,
+    debug: Option<DebugUtils>,
+    workgroup_sizes: WorkgroupSizes,
     sampler: vk::Sampler,
     params_ds_layout: vk::DescriptorSetLayout,
     render_ds_layout: vk::DescriptorSetLayout,
-    frame_ds_layout: vk::DescriptorSetLayout,
     transmittance: Pass,
     single_scattering: Pass,
     direct_irradiance: Pass,
     indirect_irradiance: Pass,
     scattering_density: Pass,
     multiple_scattering: Pass,
+    convergence: Pass,
+    validation: Pass,
 }
 
 impl Drop for Builder {
@@ -37,8 +52,6 @@ impl Drop for Builder {
                 .destroy_descriptor_set_layout(self.params_ds_layout, None);
             self.device
                 .destroy_descriptor_set_layout(self.render_ds_layout, None);
-            self.device
-                .destroy_descriptor_set_layout(self.frame_ds_layout, None);
             for &pass in &[
                 &self.transmittance,
                 &self.single_scattering,
@@ -46,6 +59,8 @@ impl Drop for Builder {
                 &self.indirect_irradiance,
                 &self.scattering_density,
                 &self.multiple_scattering,
+                &self.convergence,
+                &self.validation,
             ] {
                 self.device.destroy_pipeline(pass.pipeline, None);
                 self.device.destroy_pipeline_layout(pass.layout, None);
@@ -58,6 +73,7 @@ impl Drop for Builder {
 }
 
 impl Builder {
+    /// Construct a `Builder`, configured by `options`
     pub fn new(
         instance: &Instance,
         device: Arc<Device>,
@@ -65,19 +81,57 @@ impl Builder {
         physical: vk::PhysicalDevice,
         gfx_queue_family: u32,
         compute_queue_family: Option<u32>,
+        options: BuilderOptions,
     ) -> Self {
+        let BuilderOptions {
+            allocator,
+            quality,
+            shaders,
+            workgroup_sizes,
+        } = options;
+        let allocator = allocator.unwrap_or_else(|| {
+            let memory_props = unsafe { instance.get_physical_device_memory_properties(physical) };
+            Arc::new(DefaultAllocator::new(device.clone(), memory_props))
+        });
+        let workgroup_sizes =
+            workgroup_sizes.unwrap_or_else(|| WorkgroupSizes::for_device(instance, physical));
+        assert!(
+            quality.indirect_irradiance_samples & 1 == 0,
+            "indirect irradiance sample count must be even"
+        );
+        let limits = unsafe { instance.get_physical_device_properties(physical).limits };
+        assert!(
+            workgroup_sizes.fits(&limits),
+            "workgroup sizes exceed the device's limits"
+        );
         unsafe {
+            // Specialization constants: the sample count, then the workgroup size
+            let size_2d = workgroup_sizes.size_2d;
+            let size_3d = workgroup_sizes.size_3d;
+            let transmittance_constants = [quality.transmittance_samples, size_2d, size_2d, 1];
+            // Direct irradiance takes no samples; 0 only pads constants 1 to 3 into place
+            let direct_irradiance_constants = [0, size_2d, size_2d, 1];
+            let indirect_irradiance_constants =
+                [quality.indirect_irradiance_samples, size_2d, size_2d, 1];
+            let single_scattering_constants =
+                [quality.single_scattering_samples, size_3d, size_3d, size_3d];
+            let scattering_density_constants = [
+                quality.scattering_density_samples,
+                size_3d,
+                size_3d,
+                size_3d,
+            ];
+            let multiple_scattering_constants = [
+                quality.multiple_scattering_samples,
+                size_3d,
+                size_3d,
+                size_3d,
+            ];
+
+            let params_bindings = params_bindings();
             let params_ds_layout = device
                 .create_descriptor_set_layout(
-                    &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&[
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 0,
-                            descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: ptr::null(),
-                        },
-                    ]),
+                    &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&params_bindings),
                     None,
                 )
                 .unwrap();
@@ -97,17 +151,10 @@ impl Builder {
                 )
                 .unwrap();
 
+            let transmittance_bindings = transmittance_bindings();
             let transmittance_ds_layout = device
                 .create_descriptor_set_layout(
-                    &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&[
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 0,
-                            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: ptr::null(),
-                        },
-                    ]),
+                    &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&transmittance_bindings),
                     None,
                 )
                 .unwrap();
@@ -118,33 +165,25 @@ impl Builder {
                     None,
                 )
                 .unwrap();
-            let transmittance_shader = device
-                .create_shader_module(
-                    &vk::ShaderModuleCreateInfo::builder().code(&TRANSMITTANCE),
-                    None,
-                )
-                .unwrap();
+            let transmittance_shader = shader::create_module(
+                &device,
+                TRANSMITTANCE,
+                shaders.transmittance,
+                &Interface {
+                    name: "transmittance",
+                    stage: vk::ShaderStageFlags::COMPUTE,
+                    sets: &[&params_bindings, &transmittance_bindings],
+                    push_constant_size: 0,
+                    local_size: Some([size_2d, size_2d, 1]),
+                    specialization: &transmittance_constants,
+                },
+            );
 
+            let direct_irradiance_bindings = direct_irradiance_bindings(&sampler);
             let direct_irradiance_ds_layout = device
                 .create_descriptor_set_layout(
-                    &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&[
-                        // transmittance
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 0,
-                            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: &sampler,
-                        },
-                        // delta_irradiance
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 1,
-                            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: ptr::null(),
-                        },
-                    ]),
+                    &vk::DescriptorSetLayoutCreateInfo::builder()
+                        .bindings(&direct_irradiance_bindings),
                     None,
                 )
                 .unwrap();
@@ -155,57 +194,25 @@ impl Builder {
                     None,
                 )
                 .unwrap();
-            let direct_irradiance_shader = device
-                .create_shader_module(
-                    &vk::ShaderModuleCreateInfo::builder().code(&DIRECT_IRRADIANCE),
-                    None,
-                )
-                .unwrap();
+            let direct_irradiance_shader = shader::create_module(
+                &device,
+                DIRECT_IRRADIANCE,
+                shaders.direct_irradiance,
+                &Interface {
+                    name: "direct irradiance",
+                    stage: vk::ShaderStageFlags::COMPUTE,
+                    sets: &[&params_bindings, &direct_irradiance_bindings],
+                    push_constant_size: 0,
+                    local_size: Some([size_2d, size_2d, 1]),
+                    specialization: &direct_irradiance_constants,
+                },
+            );
 
+            let indirect_irradiance_bindings = indirect_irradiance_bindings(&sampler);
             let indirect_irradiance_ds_layout = device
                 .create_descriptor_set_layout(
-                    &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&[
-                        // single_rayleigh
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 0,
-                            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: &sampler,
-                        },
-                        // single_mie
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 1,
-                            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: &sampler,
-                        },
-                        // multiple
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 2,
-                            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: &sampler,
-                        },
-                        // delta_irradiance
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 3,
-                            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: ptr::null(),
-                        },
-                        // irradiance
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 4,
-                            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: ptr::null(),
-                        },
-                    ]),
+                    &vk::DescriptorSetLayoutCreateInfo::builder()
+                        .bindings(&indirect_irradiance_bindings),
                     None,
                 )
                 .unwrap();
@@ -221,49 +228,24 @@ impl Builder {
                     None,
                 )
                 .unwrap();
-            let indirect_irradiance_shader = device
-                .create_shader_module(
-                    &vk::ShaderModuleCreateInfo::builder().code(&INDIRECT_IRRADIANCE),
-                    None,
-                )
-                .unwrap();
+            let indirect_irradiance_shader = shader::create_module(
+                &device,
+                INDIRECT_IRRADIANCE,
+                shaders.indirect_irradiance,
+                &Interface {
+                    name: "indirect irradiance",
+                    stage: vk::ShaderStageFlags::COMPUTE,
+                    sets: &[&params_bindings, &indirect_irradiance_bindings],
+                    push_constant_size: 4,
+                    local_size: Some([size_2d, size_2d, 1]),
+                    specialization: &indirect_irradiance_constants,
+                },
+            );
 
+            let scattering_bindings = scattering_bindings(&sampler);
             let scattering_ds_layout = device
                 .create_descriptor_set_layout(
-                    &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&[
-                        // transmittance
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 0,
-                            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: &sampler,
-                        },
-                        // delta_rayleigh
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 1,
-                            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: ptr::null(),
-                        },
-                        // delta_mie
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 2,
-                            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: ptr::null(),
-                        },
-                        // scattering
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 3,
-                            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: ptr::null(),
-                        },
-                    ]),
+                    &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&scattering_bindings),
                     None,
                 )
                 .unwrap();
@@ -274,65 +256,25 @@ impl Builder {
                     None,
                 )
                 .unwrap();
-            let scattering_shader = device
-                .create_shader_module(
-                    &vk::ShaderModuleCreateInfo::builder().code(&SINGLE_SCATTERING),
-                    None,
-                )
-                .unwrap();
+            let scattering_shader = shader::create_module(
+                &device,
+                SINGLE_SCATTERING,
+                shaders.single_scattering,
+                &Interface {
+                    name: "single scattering",
+                    stage: vk::ShaderStageFlags::COMPUTE,
+                    sets: &[&params_bindings, &scattering_bindings],
+                    push_constant_size: 0,
+                    local_size: Some([size_3d; 3]),
+                    specialization: &single_scattering_constants,
+                },
+            );
 
+            let scattering_density_bindings = scattering_density_bindings(&sampler);
             let scattering_density_ds_layout = device
                 .create_descriptor_set_layout(
-                    &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&[
-                        // transmittance
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 0,
-                            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: &sampler,
-                        },
-                        // single_rayleigh
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 1,
-                            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: &sampler,
-                        },
-                        // single_mie
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 2,
-                            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: &sampler,
-                        },
-                        // multiple_scattering
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 3,
-                            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: &sampler,
-                        },
-                        // irradiance
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 4,
-                            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: &sampler,
-                        },
-                        // scattering_density
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 5,
-                            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: ptr::null(),
-                        },
-                    ]),
+                    &vk::DescriptorSetLayoutCreateInfo::builder()
+                        .bindings(&scattering_density_bindings),
                     None,
                 )
                 .unwrap();
@@ -348,49 +290,25 @@ impl Builder {
                     None,
                 )
                 .unwrap();
-            let scattering_density_shader = device
-                .create_shader_module(
-                    &vk::ShaderModuleCreateInfo::builder().code(&SCATTERING_DENSITY),
-                    None,
-                )
-                .unwrap();
+            let scattering_density_shader = shader::create_module(
+                &device,
+                SCATTERING_DENSITY,
+                shaders.scattering_density,
+                &Interface {
+                    name: "scattering density",
+                    stage: vk::ShaderStageFlags::COMPUTE,
+                    sets: &[&params_bindings, &scattering_density_bindings],
+                    push_constant_size: 4,
+                    local_size: Some([size_3d; 3]),
+                    specialization: &scattering_density_constants,
+                },
+            );
 
+            let multiple_scattering_bindings = multiple_scattering_bindings(&sampler);
             let multiple_scattering_ds_layout = device
                 .create_descriptor_set_layout(
-                    &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&[
-                        // transmittance
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 0,
-                            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: &sampler,
-                        },
-                        // scattering_density
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 1,
-                            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: &sampler,
-                        },
-                        // delta_multiple_scattering
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 2,
-                            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: ptr::null(),
-                        },
-                        // scattering
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 3,
-                            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::COMPUTE,
-                            p_immutable_samplers: ptr::null(),
-                        },
-                    ]),
+                    &vk::DescriptorSetLayoutCreateInfo::builder()
+                        .bindings(&multiple_scattering_bindings),
                     None,
                 )
                 .unwrap();
@@ -406,59 +324,126 @@ impl Builder {
                     None,
                 )
                 .unwrap();
-            let multiple_scattering_shader = device
-                .create_shader_module(
-                    &vk::ShaderModuleCreateInfo::builder().code(&MULTIPLE_SCATTERING),
+            let multiple_scattering_shader = shader::create_module(
+                &device,
+                MULTIPLE_SCATTERING,
+                shaders.multiple_scattering,
+                &Interface {
+                    name: "multiple scattering",
+                    stage: vk::ShaderStageFlags::COMPUTE,
+                    sets: &[&params_bindings, &multiple_scattering_bindings],
+                    push_constant_size: 4,
+                    local_size: Some([size_3d; 3]),
+                    specialization: &multiple_scattering_constants,
+                },
+            );
+
+            let convergence_bindings = convergence_bindings();
+            let convergence_ds_layout = device
+                .create_descriptor_set_layout(
+                    &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&convergence_bindings),
+                    None,
+                )
+                .unwrap();
+            let convergence_layout = device
+                .create_pipeline_layout(
+                    &vk::PipelineLayoutCreateInfo::builder()
+                        .set_layouts(&[params_ds_layout, convergence_ds_layout])
+                        .push_constant_ranges(&[vk::PushConstantRange {
+                            stage_flags: vk::ShaderStageFlags::COMPUTE,
+                            offset: 0,
+                            size: 8,
+                        }]),
                     None,
                 )
                 .unwrap();
+            let convergence_shader = shader::create_module(
+                &device,
+                CONVERGENCE,
+                shaders.convergence,
+                &Interface {
+                    name: "convergence",
+                    stage: vk::ShaderStageFlags::COMPUTE,
+                    sets: &[&params_bindings, &convergence_bindings],
+                    push_constant_size: 8,
+                    local_size: Some([CONVERGENCE_WORKGROUP_SIZE, 1, 1]),
+                    specialization: &[],
+                },
+            );
 
-            let render_ds_layout = device
+            let validation_bindings = validation_bindings(&sampler);
+            let validation_ds_layout = device
                 .create_descriptor_set_layout(
-                    &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&[
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 0,
-                            descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::FRAGMENT,
-                            p_immutable_samplers: ptr::null(),
-                        },
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 1,
-                            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::FRAGMENT,
-                            p_immutable_samplers: &sampler,
-                        },
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 2,
-                            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::FRAGMENT,
-                            p_immutable_samplers: &sampler,
-                        },
-                    ]),
+                    &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&validation_bindings),
+                    None,
+                )
+                .unwrap();
+            let validation_layout = device
+                .create_pipeline_layout(
+                    &vk::PipelineLayoutCreateInfo::builder()
+                        .set_layouts(&[params_ds_layout, validation_ds_layout])
+                        .push_constant_ranges(&[vk::PushConstantRange {
+                            stage_flags: vk::ShaderStageFlags::COMPUTE,
+                            offset: 0,
+                            size: 4,
+                        }]),
                     None,
                 )
                 .unwrap();
+            let validation_shader = shader::create_module(
+                &device,
+                VALIDATE_LUTS,
+                shaders.validate_luts,
+                &Interface {
+                    name: "validate_luts",
+                    stage: vk::ShaderStageFlags::COMPUTE,
+                    sets: &[&params_bindings, &validation_bindings],
+                    push_constant_size: 4,
+                    local_size: None,
+                    specialization: &[],
+                },
+            );
 
-            let frame_ds_layout = device
+            let render_bindings = render_bindings(&sampler);
+            let render_ds_layout = device
                 .create_descriptor_set_layout(
-                    &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&[
-                        vk::DescriptorSetLayoutBinding {
-                            binding: 0,
-                            descriptor_type: vk::DescriptorType::INPUT_ATTACHMENT,
-                            descriptor_count: 1,
-                            stage_flags: vk::ShaderStageFlags::FRAGMENT,
-                            p_immutable_samplers: ptr::null(),
-                        },
-                    ]),
+                    &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&render_bindings),
                     None,
                 )
                 .unwrap();
 
             let p_name = b"main\0".as_ptr() as *const i8;
 
+            let constant_entry = |constant_id: u32| vk::SpecializationMapEntry {
+                constant_id,
+                offset: constant_id * mem::size_of::<u32>() as u32,
+                size: mem::size_of::<u32>(),
+            };
+            let constant_entries = [
+                constant_entry(0),
+                constant_entry(1),
+                constant_entry(2),
+                constant_entry(3),
+            ];
+            let transmittance_specialization = vk::SpecializationInfo::builder()
+                .map_entries(&constant_entries)
+                .data(as_bytes(&transmittance_constants));
+            let direct_irradiance_specialization = vk::SpecializationInfo::builder()
+                .map_entries(&constant_entries[1..])
+                .data(as_bytes(&direct_irradiance_constants));
+            let single_scattering_specialization = vk::SpecializationInfo::builder()
+                .map_entries(&constant_entries)
+                .data(as_bytes(&single_scattering_constants));
+            let scattering_density_specialization = vk::SpecializationInfo::builder()
+                .map_entries(&constant_entries)
+                .data(as_bytes(&scattering_density_constants));
+            let indirect_irradiance_specialization = vk::SpecializationInfo::builder()
+                .map_entries(&constant_entries)
+                .data(as_bytes(&indirect_irradiance_constants));
+            let multiple_scattering_specialization = vk::SpecializationInfo::builder()
+                .map_entries(&constant_entries)
+                .data(as_bytes(&multiple_scattering_constants));
+
             let mut pipelines = device
                 .create_compute_pipelines(
                     cache,
@@ -468,6 +453,7 @@ impl Builder {
                                 stage: vk::ShaderStageFlags::COMPUTE,
                                 module: transmittance_shader,
                                 p_name,
+                                p_specialization_info: &*transmittance_specialization,
                                 ..Default::default()
                             },
                             layout: transmittance_layout,
@@ -478,6 +464,7 @@ impl Builder {
                                 stage: vk::ShaderStageFlags::COMPUTE,
                                 module: direct_irradiance_shader,
                                 p_name,
+                                p_specialization_info: &*direct_irradiance_specialization,
                                 ..Default::default()
                             },
                             layout: direct_irradiance_layout,
@@ -488,6 +475,7 @@ impl Builder {
                                 stage: vk::ShaderStageFlags::COMPUTE,
                                 module: indirect_irradiance_shader,
                                 p_name,
+                                p_specialization_info: &*indirect_irradiance_specialization,
                                 ..Default::default()
                             },
                             layout: indirect_irradiance_layout,
@@ -498,6 +486,7 @@ impl Builder {
                                 stage: vk::ShaderStageFlags::COMPUTE,
                                 module: scattering_shader,
                                 p_name,
+                                p_specialization_info: &*single_scattering_specialization,
                                 ..Default::default()
                             },
                             layout: scattering_layout,
@@ -508,6 +497,7 @@ impl Builder {
                                 stage: vk::ShaderStageFlags::COMPUTE,
                                 module: scattering_density_shader,
                                 p_name,
+                                p_specialization_info: &*scattering_density_specialization,
                                 ..Default::default()
                             },
                             layout: scattering_density_layout,
@@ -518,11 +508,32 @@ impl Builder {
                                 stage: vk::ShaderStageFlags::COMPUTE,
                                 module: multiple_scattering_shader,
                                 p_name,
+                                p_specialization_info: &*multiple_scattering_specialization,
                                 ..Default::default()
                             },
                             layout: multiple_scattering_layout,
                             ..Default::default()
                         },
+                        vk::ComputePipelineCreateInfo {
+                            stage: vk::PipelineShaderStageCreateInfo {
+                                stage: vk::ShaderStageFlags::COMPUTE,
+                                module: convergence_shader,
+                                p_name,
+                                ..Default::default()
+                            },
+                            layout: convergence_layout,
+                            ..Default::default()
+                        },
+                        vk::ComputePipelineCreateInfo {
+                            stage: vk::PipelineShaderStageCreateInfo {
+                                stage: vk::ShaderStageFlags::COMPUTE,
+                                module: validation_shader,
+                                p_name,
+                                ..Default::default()
+                            },
+                            layout: validation_layout,
+                            ..Default::default()
+                        },
                     ],
                     None,
                 )
@@ -565,45 +576,183 @@ impl Builder {
                 layout: multiple_scattering_layout,
                 ds_layout: multiple_scattering_ds_layout,
             };
+            let convergence = Pass {
+                shader: convergence_shader,
+                pipeline: pipelines.next().unwrap(),
+                layout: convergence_layout,
+                ds_layout: convergence_ds_layout,
+            };
+            let validation = Pass {
+                shader: validation_shader,
+                pipeline: pipelines.next().unwrap(),
+                layout: validation_layout,
+                ds_layout: validation_ds_layout,
+            };
             debug_assert!(pipelines.next().is_none());
 
+            let debug = DebugUtils::new(instance, &device);
+            if let Some(ref debug) = debug {
+                for &(pass, name) in &[
+                    (&transmittance, "fuzzyblue transmittance"),
+                    (&direct_irradiance, "fuzzyblue direct irradiance"),
+                    (&indirect_irradiance, "fuzzyblue indirect irradiance"),
+                    (&single_scattering, "fuzzyblue single scattering"),
+                    (&scattering_density, "fuzzyblue scattering density"),
+                    (&multiple_scattering, "fuzzyblue multiple scattering"),
+                    (&convergence, "fuzzyblue convergence"),
+                    (&validation, "fuzzyblue validation"),
+                ] {
+                    debug.name(pass.pipeline, name);
+                }
+            }
+
             Self {
                 device,
                 memory_props: instance.get_physical_device_memory_properties(physical),
+                allocator,
                 gfx_queue_family,
                 compute_queue_family,
+                timestamp_period: instance
+                    .get_physical_device_properties(physical)
+                    .limits
+                    .timestamp_period,
+                timestamp_mask: {
+                    let bits = instance.get_physical_device_queue_family_properties(physical)
+                        [compute_queue_family.unwrap_or(gfx_queue_family) as usize]
+                        .timestamp_valid_bits;
+                    u64::MAX.checked_shr(64 - bits).unwrap_or(0)
+                },
+                debug,
+                workgroup_sizes,
                 sampler,
                 params_ds_layout,
                 render_ds_layout,
-                frame_ds_layout,
                 transmittance,
                 direct_irradiance,
                 indirect_irradiance,
                 single_scattering,
                 scattering_density,
                 multiple_scattering,
+                convergence,
+                validation,
+            }
+        }
+    }
+
+    /// Device memory that building an `Atmosphere` from `params` will allocate
+    ///
+    /// Doesn't account for allocation granularity or overhead within the driver.
+    pub fn memory_report(&self, params: &Parameters) -> MemoryReport {
+        let device = &*self.device;
+        unsafe {
+            let image_reqs = |info: &vk::ImageCreateInfo| {
+                let image = device.create_image(info, None).unwrap();
+                let reqs = device.get_image_memory_requirements(image);
+                device.destroy_image(image, None);
+                reqs
+            };
+            let buffer_size = |size, usage| {
+                let buffer = device
+                    .create_buffer(
+                        &vk::BufferCreateInfo {
+                            size,
+                            usage,
+                            ..Default::default()
+                        },
+                        None,
+                    )
+                    .unwrap();
+                let size = device.get_buffer_memory_requirements(buffer).size;
+                device.destroy_buffer(buffer, None);
+                size
+            };
+            let params_size = buffer_size(
+                mem::size_of::<ParamsRaw>() as vk::DeviceSize,
+                vk::BufferUsageFlags::UNIFORM_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
+            );
+
+            let mut persistent_infos = vec![
+                params.transmittance_image_info(),
+                params.irradiance_image_info(),
+                params.directional_irradiance_image_info(),
+                params.scattering_image_info(),
+            ];
+            if params.retain_single_scattering {
+                persistent_infos.push(params.scattering_image_info());
+                persistent_infos.push(params.scattering_image_info());
+            }
+            let persistent = params_size
+                + persistent_infos
+                    .iter()
+                    .map(|info| image_reqs(info).size)
+                    .sum::<vk::DeviceSize>();
+            let temporary = pack(
+                &params
+                    .temporary_image_infos()
+                    .iter()
+                    .map(image_reqs)
+                    .collect::<Vec<_>>(),
+            )
+            .1
+            .size;
+            // Host-visible buffers for results read back after precompute
+            let mut readback = 0;
+            if params.convergence_threshold.is_some() {
+                readback += buffer_size(
+                    convergence_size(params.scattering_extent()),
+                    vk::BufferUsageFlags::STORAGE_BUFFER
+                        | vk::BufferUsageFlags::INDIRECT_BUFFER
+                        | vk::BufferUsageFlags::TRANSFER_DST,
+                );
+            }
+            if params.validate_luts {
+                readback += buffer_size(
+                    mem::size_of::<LutReportRaw>() as vk::DeviceSize,
+                    vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
+                );
+            }
+            if params.export_orders {
+                let size =
+                    export_offsets(params.irradiance_extent(), params.scattering_extent())[2];
+                readback += buffer_size(size, vk::BufferUsageFlags::TRANSFER_DST)
+                    * vk::DeviceSize::from(params.order.max(1));
+            }
+            MemoryReport {
+                persistent,
+                peak: persistent + temporary + readback,
             }
         }
     }
 
-    unsafe fn alloc_image(&self, info: &vk::ImageCreateInfo) -> Image {
+    unsafe fn alloc_image(&self, info: &vk::ImageCreateInfo, name: &str) -> Image {
         let handle = self.device.create_image(info, None).unwrap();
         let reqs = self.device.get_image_memory_requirements(handle);
-        let memory = allocate(
-            &self.device,
-            &self.memory_props,
-            reqs,
-            vk::MemoryPropertyFlags::DEVICE_LOCAL,
-        )
-        .unwrap();
-        self.device.bind_image_memory(handle, memory, 0).unwrap();
-        let view = self
-            .device
+        let memory = self
+            .allocator
+            .allocate(reqs, vk::MemoryPropertyFlags::DEVICE_LOCAL);
+        self.device
+            .bind_image_memory(handle, memory.memory, memory.offset)
+            .unwrap();
+        let view = self.create_view(handle, info);
+        self.name(handle, name);
+        self.name(view, &format!("{} view", name));
+        Image {
+            handle,
+            view,
+            memory,
+        }
+    }
+
+    unsafe fn create_view(&self, image: vk::Image, info: &vk::ImageCreateInfo) -> vk::ImageView {
+        self.device
             .create_image_view(
                 &vk::ImageViewCreateInfo {
-                    image: handle,
+                    image,
                     view_type: match info.image_type {
                         vk::ImageType::TYPE_1D => vk::ImageViewType::TYPE_1D,
+                        vk::ImageType::TYPE_2D if info.array_layers > 1 => {
+                            vk::ImageViewType::TYPE_2D_ARRAY
+                        }
                         vk::ImageType::TYPE_2D => vk::ImageViewType::TYPE_2D,
                         vk::ImageType::TYPE_3D => vk::ImageViewType::TYPE_3D,
                         _ => unreachable!("unknown image type"),
@@ -620,18 +769,13 @@ impl Builder {
                         base_mip_level: 0,
                         level_count: 1,
                         base_array_layer: 0,
-                        layer_count: 1,
+                        layer_count: info.array_layers,
                     },
                     ..Default::default()
                 },
                 None,
             )
-            .unwrap();
-        Image {
-            handle,
-            view,
-            memory,
-        }
+            .unwrap()
     }
 
     pub(crate) fn device(&self) -> &Arc<Device> {
@@ -641,35 +785,370 @@ impl Builder {
     pub(crate) fn render_ds_layout(&self) -> vk::DescriptorSetLayout {
         self.render_ds_layout
     }
-    pub(crate) fn frame_ds_layout(&self) -> vk::DescriptorSetLayout {
-        self.frame_ds_layout
+    pub(crate) fn sampler(&self) -> vk::Sampler {
+        self.sampler
+    }
+    pub(crate) fn gfx_queue_family(&self) -> u32 {
+        self.gfx_queue_family
+    }
+    pub(crate) fn compute_queue_family(&self) -> Option<u32> {
+        self.compute_queue_family
+    }
+    pub(crate) fn memory_props(&self) -> &vk::PhysicalDeviceMemoryProperties {
+        &self.memory_props
+    }
+    pub(crate) fn debug(&self) -> Option<&DebugUtils> {
+        self.debug.as_ref()
+    }
+
+    /// Name `object` for debugging tools, if `VK_EXT_debug_utils` is available
+    pub(crate) unsafe fn name<T: Handle>(&self, object: T, name: &str) {
+        if let Some(ref debug) = self.debug {
+            debug.name(object, name);
+        }
     }
 }
 
-struct Image {
-    handle: vk::Image,
-    view: vk::ImageView,
-    memory: vk::DeviceMemory,
+/// Bindings of the descriptor set shared by all precompute passes, holding `Parameters`
+fn params_bindings() -> [vk::DescriptorSetLayoutBinding; 1] {
+    [vk::DescriptorSetLayoutBinding {
+        binding: 0,
+        descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+        descriptor_count: 1,
+        stage_flags: vk::ShaderStageFlags::COMPUTE,
+        p_immutable_samplers: ptr::null(),
+    }]
 }
 
-/// A single layer of a `DensityProfile`
-///
-/// An atmosphere layer of width 'width', and whose density is defined as
-///   'exp_term' * exp('exp_scale' * h) + 'linear_term' * h + 'constant_term',
-/// clamped to [0,1], and where h is the altitude.
-pub struct DensityProfileLayer {
-    pub width: f32,
-    pub exp_term: f32,
-    pub exp_scale: f32,
-    pub linear_term: f32,
-    pub constant_term: f32,
+/// Bindings of the transmittance pass's descriptor set
+fn transmittance_bindings() -> [vk::DescriptorSetLayoutBinding; 1] {
+    [vk::DescriptorSetLayoutBinding {
+        binding: 0,
+        descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+        descriptor_count: 1,
+        stage_flags: vk::ShaderStageFlags::COMPUTE,
+        p_immutable_samplers: ptr::null(),
+    }]
 }
 
-/// A collection of `DensityProfileLayer`s
-///
-/// An atmosphere density profile made of several layers on top of each other
-/// (from bottom to top). The width of the last layer is ignored, i.e. it always
-/// extend to the top atmosphere boundary. The profile values vary between 0
+/// Bindings of the direct irradiance pass's descriptor set
+fn direct_irradiance_bindings(sampler: &vk::Sampler) -> [vk::DescriptorSetLayoutBinding; 2] {
+    [
+        // transmittance
+        vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: sampler,
+        },
+        // delta_irradiance
+        vk::DescriptorSetLayoutBinding {
+            binding: 1,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: ptr::null(),
+        },
+    ]
+}
+
+/// Bindings of the indirect irradiance pass's descriptor set
+fn indirect_irradiance_bindings(sampler: &vk::Sampler) -> [vk::DescriptorSetLayoutBinding; 6] {
+    [
+        // single_rayleigh
+        vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: sampler,
+        },
+        // single_mie
+        vk::DescriptorSetLayoutBinding {
+            binding: 1,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: sampler,
+        },
+        // multiple
+        vk::DescriptorSetLayoutBinding {
+            binding: 2,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: sampler,
+        },
+        // delta_irradiance
+        vk::DescriptorSetLayoutBinding {
+            binding: 3,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: ptr::null(),
+        },
+        // irradiance
+        vk::DescriptorSetLayoutBinding {
+            binding: 4,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: ptr::null(),
+        },
+        // directional_irradiance
+        vk::DescriptorSetLayoutBinding {
+            binding: 5,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: ptr::null(),
+        },
+    ]
+}
+
+/// Bindings of the single scattering pass's descriptor set
+fn scattering_bindings(sampler: &vk::Sampler) -> [vk::DescriptorSetLayoutBinding; 4] {
+    [
+        // transmittance
+        vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: sampler,
+        },
+        // delta_rayleigh
+        vk::DescriptorSetLayoutBinding {
+            binding: 1,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: ptr::null(),
+        },
+        // delta_mie
+        vk::DescriptorSetLayoutBinding {
+            binding: 2,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: ptr::null(),
+        },
+        // scattering
+        vk::DescriptorSetLayoutBinding {
+            binding: 3,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: ptr::null(),
+        },
+    ]
+}
+
+/// Bindings of the scattering density pass's descriptor set
+fn scattering_density_bindings(sampler: &vk::Sampler) -> [vk::DescriptorSetLayoutBinding; 6] {
+    [
+        // transmittance
+        vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: sampler,
+        },
+        // single_rayleigh
+        vk::DescriptorSetLayoutBinding {
+            binding: 1,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: sampler,
+        },
+        // single_mie
+        vk::DescriptorSetLayoutBinding {
+            binding: 2,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: sampler,
+        },
+        // multiple_scattering
+        vk::DescriptorSetLayoutBinding {
+            binding: 3,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: sampler,
+        },
+        // irradiance
+        vk::DescriptorSetLayoutBinding {
+            binding: 4,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: sampler,
+        },
+        // scattering_density
+        vk::DescriptorSetLayoutBinding {
+            binding: 5,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: ptr::null(),
+        },
+    ]
+}
+
+/// Bindings of the multiple scattering pass's descriptor set
+fn multiple_scattering_bindings(sampler: &vk::Sampler) -> [vk::DescriptorSetLayoutBinding; 4] {
+    [
+        // transmittance
+        vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: sampler,
+        },
+        // scattering_density
+        vk::DescriptorSetLayoutBinding {
+            binding: 1,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: sampler,
+        },
+        // delta_multiple_scattering
+        vk::DescriptorSetLayoutBinding {
+            binding: 2,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: ptr::null(),
+        },
+        // scattering
+        vk::DescriptorSetLayoutBinding {
+            binding: 3,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: ptr::null(),
+        },
+    ]
+}
+
+/// Bindings of the convergence pass's descriptor set
+fn convergence_bindings() -> [vk::DescriptorSetLayoutBinding; 3] {
+    [
+        vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: ptr::null(),
+        },
+        vk::DescriptorSetLayoutBinding {
+            binding: 1,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: ptr::null(),
+        },
+        vk::DescriptorSetLayoutBinding {
+            binding: 2,
+            descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: ptr::null(),
+        },
+    ]
+}
+
+/// Bindings of the validation pass's descriptor set
+fn validation_bindings(sampler: &vk::Sampler) -> [vk::DescriptorSetLayoutBinding; 4] {
+    [
+        vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: sampler,
+        },
+        vk::DescriptorSetLayoutBinding {
+            binding: 1,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: sampler,
+        },
+        vk::DescriptorSetLayoutBinding {
+            binding: 2,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: sampler,
+        },
+        vk::DescriptorSetLayoutBinding {
+            binding: 3,
+            descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: ptr::null(),
+        },
+    ]
+}
+
+/// Bindings of `Builder::render_ds_layout`, which holds an `Atmosphere`'s look-up tables
+pub(crate) fn render_bindings(sampler: &vk::Sampler) -> [vk::DescriptorSetLayoutBinding; 3] {
+    [
+        vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::FRAGMENT | vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: ptr::null(),
+        },
+        vk::DescriptorSetLayoutBinding {
+            binding: 1,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::FRAGMENT | vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: sampler,
+        },
+        vk::DescriptorSetLayoutBinding {
+            binding: 2,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::FRAGMENT | vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: sampler,
+        },
+    ]
+}
+
+struct Image {
+    handle: vk::Image,
+    view: vk::ImageView,
+    memory: Allocation,
+}
+
+/// A single layer of a `DensityProfile`
+///
+/// An atmosphere layer of width 'width', and whose density is defined as
+///   'exp_term' * exp('exp_scale' * h) + 'linear_term' * h + 'constant_term',
+/// clamped to [0,1], and where h is the altitude.
+pub struct DensityProfileLayer {
+    pub width: f32,
+    pub exp_term: f32,
+    pub exp_scale: f32,
+    pub linear_term: f32,
+    pub constant_term: f32,
+}
+
+/// A collection of `DensityProfileLayer`s
+///
+/// An atmosphere density profile made of several layers on top of each other
+/// (from bottom to top). The width of the last layer is ignored, i.e. it always
+/// extend to the top atmosphere boundary. The profile values vary between 0
 /// (null density) to 1 (maximum density).
 pub struct DensityProfile {
     pub layers: [DensityProfileLayer; 2],
@@ -677,7 +1156,8 @@ pub struct DensityProfile {
 
 /// Parameters governing generated skies
 ///
-/// Distances in km.
+/// Distances in km. Look-up tables are computed for unit solar irradiance, which is applied when
+/// rendering by `DrawParameters::solar_irradiance`.
 ///
 /// # LUT dimensions
 ///
@@ -697,8 +1177,32 @@ pub struct Parameters {
     /// Layout the look-up tables should end in
     pub layout: vk::ImageLayout,
 
-    /// Number of light bounces to simulate
+    /// Number of light bounces to simulate, or the maximum if `convergence_threshold` is set
     pub order: u32,
+    /// Stop adding light bounces once one adds less than this fraction of the total
+    ///
+    /// Convergence is measured on the GPU, so every order up to `order` is still recorded, but
+    /// those past convergence do no work. See `Atmosphere::scattering_order` for the result.
+    pub convergence_threshold: Option<f32>,
+    /// Write GPU timestamps around each precompute step, for `PendingAtmosphere::timings`
+    ///
+    /// The queue family used for precompute must support timestamps.
+    pub record_timings: bool,
+    /// Keep single scattering after precompute so `Atmosphere::rebuild` can skip recomputing it
+    ///
+    /// Costs two scattering-sized images for the lifetime of the `Atmosphere`. Worthwhile when
+    /// rebuilds frequently change only `ground_albedo`, `mie_phase_function_g`,
+    /// `sun_angular_radius`, or `order`.
+    pub retain_single_scattering: bool,
+    /// Scan the finished look-up tables for non-finite and negative texels, for
+    /// `PendingAtmosphere::lut_report`
+    pub validate_luts: bool,
+    /// Copy each scattering order's intermediate results to host memory, for
+    /// `PendingAtmosphere::order_exports`
+    ///
+    /// Costs one irradiance-sized and two scattering-sized tables of host-visible memory per order
+    /// until the `PendingAtmosphere` is dropped.
+    pub export_orders: bool,
 
     /// View angle precision for the transmittance look-up table
     pub transmittance_mu_size: u32,
@@ -717,8 +1221,6 @@ pub struct Parameters {
     /// Height precision for the lighting look-up table
     pub irradiance_r_size: u32,
 
-    /// The solar irradiance at the top of the atmosphere.
-    pub solar_irradiance: [f32; 3],
     /// The sun's angular radius. Warning: the implementation uses approximations
     /// that are valid only if this angle is smaller than 0.1 radians.
     pub sun_angular_radius: f32,
@@ -790,6 +1292,254 @@ impl Parameters {
             depth: self.scattering_r_size,
         }
     }
+
+    fn transmittance_image_info(&self) -> vk::ImageCreateInfo {
+        let extent = self.transmittance_extent();
+        vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            format: vk::Format::R32G32B32A32_SFLOAT,
+            extent: vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            mip_levels: 1,
+            array_layers: 1,
+            samples: vk::SampleCountFlags::TYPE_1,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage: vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED | self.usage,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            ..Default::default()
+        }
+    }
+
+    fn irradiance_image_info(&self) -> vk::ImageCreateInfo {
+        let extent = self.irradiance_extent();
+        vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            format: vk::Format::R32G32B32A32_SFLOAT,
+            extent: vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            mip_levels: 1,
+            array_layers: 1,
+            samples: vk::SampleCountFlags::TYPE_1,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage: vk::ImageUsageFlags::STORAGE
+                | vk::ImageUsageFlags::SAMPLED
+                | vk::ImageUsageFlags::TRANSFER_DST
+                | self.usage,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            ..Default::default()
+        }
+    }
+
+    fn directional_irradiance_image_info(&self) -> vk::ImageCreateInfo {
+        vk::ImageCreateInfo {
+            array_layers: DIRECTIONAL_IRRADIANCE_LAYERS,
+            ..self.irradiance_image_info()
+        }
+    }
+
+    fn scattering_image_info(&self) -> vk::ImageCreateInfo {
+        let mut usage = vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED | self.usage;
+        if self.retain_single_scattering {
+            // Rebuilds copy retained single scattering into the scattering texture
+            usage |= vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST;
+        }
+        vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_3D,
+            format: vk::Format::R16G16B16A16_SFLOAT,
+            extent: self.scattering_extent(),
+            mip_levels: 1,
+            array_layers: 1,
+            samples: vk::SampleCountFlags::TYPE_1,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            ..Default::default()
+        }
+    }
+
+    /// delta_irradiance, delta_rayleigh (shared with delta_multiple_scattering),
+    /// scattering_density, and delta_mie, or if single scattering is retained, delta_irradiance,
+    /// delta_multiple_scattering, and scattering_density
+    fn temporary_image_infos(&self) -> Vec<vk::ImageCreateInfo> {
+        let mut infos = vec![
+            self.irradiance_image_info(),
+            self.scattering_image_info(),
+            self.scattering_image_info(),
+        ];
+        if !self.retain_single_scattering {
+            infos.push(self.scattering_image_info());
+        }
+        if self.export_orders {
+            // Copied out after each order
+            for info in &mut infos {
+                info.usage |= vk::ImageUsageFlags::TRANSFER_SRC;
+            }
+        }
+        infos
+    }
+}
+
+/// Numerical integration effort for precompute, trading build time for accuracy
+///
+/// The worst errors lie along grazing rays for the path integrals and near the Mie forward peak for
+/// the integrals over directions. Multiple scattering contributes a minority of sky radiance, so
+/// its passes' errors are diluted in the final look-up tables. The `quality` example measures each
+/// level's error in the look-up tables against a much finer reference build.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Quality {
+    /// Steps along each ray when computing transmittance
+    pub transmittance_samples: u32,
+    /// Steps along each ray when computing single scattering
+    pub single_scattering_samples: u32,
+    /// Polar angle steps over incident directions when computing scattering density
+    pub scattering_density_samples: u32,
+    /// Polar angle steps over the sphere when computing indirect irradiance; must be even
+    pub indirect_irradiance_samples: u32,
+    /// Steps along each ray when computing multiple scattering
+    pub multiple_scattering_samples: u32,
+}
+
+impl Quality {
+    /// Faster builds, for previews or frequent rebuilds
+    pub const LOW: Self = Self {
+        transmittance_samples: 100,
+        single_scattering_samples: 25,
+        scattering_density_samples: 8,
+        indirect_irradiance_samples: 16,
+        multiple_scattering_samples: 25,
+    };
+    /// The default, matching the reference implementation's sample counts
+    pub const MEDIUM: Self = Self {
+        transmittance_samples: 500,
+        single_scattering_samples: 50,
+        scattering_density_samples: 16,
+        indirect_irradiance_samples: 32,
+        multiple_scattering_samples: 50,
+    };
+    /// Slower builds, for offline use or reference images
+    pub const HIGH: Self = Self {
+        transmittance_samples: 2000,
+        single_scattering_samples: 200,
+        scattering_density_samples: 32,
+        indirect_irradiance_samples: 64,
+        multiple_scattering_samples: 200,
+    };
+}
+
+impl Default for Quality {
+    fn default() -> Self {
+        Self::MEDIUM
+    }
+}
+
+/// Optional configuration of a `Builder`
+#[derive(Clone, Default)]
+pub struct BuilderOptions<'a> {
+    /// Source of look-up table and other memory, or `None` to allocate directly from the device
+    pub allocator: Option<Arc<dyn Allocator>>,
+    /// Numerical integration effort
+    pub quality: Quality,
+    /// Replacements for the built-in shaders
+    pub shaders: PrecomputeShaders<'a>,
+    /// Edge lengths of precompute workgroups, or `None` for `WorkgroupSizes::for_device`
+    pub workgroup_sizes: Option<WorkgroupSizes>,
+}
+
+/// Replacement SPIR-V for the compute shaders run by `Builder`, or `None` to use the built-in one
+///
+/// Each replacement must have a `main` entry point, and may only use descriptors and push constants
+/// that the shader it replaces has access to. Images must be declared with the same dimensionality
+/// and, for storage images, format. See the shaders directory for those interfaces.
+/// Sample counts from `Quality` are supplied as specialization constant 0, except to
+/// `direct_irradiance`, which takes no samples and leaves it unspecialized. The edges of the
+/// workgroup are supplied as constants 1 to 3, which replacements of all but `convergence` and
+/// `validate_luts` must take their workgroup size from. `Builder` panics if a replacement doesn't
+/// fit.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct PrecomputeShaders<'a> {
+    pub transmittance: Option<&'a [u32]>,
+    pub direct_irradiance: Option<&'a [u32]>,
+    pub single_scattering: Option<&'a [u32]>,
+    pub scattering_density: Option<&'a [u32]>,
+    pub indirect_irradiance: Option<&'a [u32]>,
+    pub multiple_scattering: Option<&'a [u32]>,
+    /// Decides whether to stop adding scattering orders when `convergence_threshold` is set, with a
+    /// workgroup size of 256 by 1 by 1
+    pub convergence: Option<&'a [u32]>,
+    /// Scans the look-up tables when `validate_luts` is set
+    pub validate_luts: Option<&'a [u32]>,
+}
+
+/// Edge lengths of the square and cubic workgroups that precompute 2D and 3D look-up tables
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct WorkgroupSizes {
+    pub size_2d: u32,
+    pub size_3d: u32,
+}
+
+impl WorkgroupSizes {
+    /// Sizes suited to `physical`, as used by `Builder::new` unless overridden
+    ///
+    /// Each is the smallest power of two whose workgroups span at least one subgroup, within the
+    /// device's limits. Subgroup size is estimated from the vendor, since querying it requires
+    /// Vulkan 1.1.
+    pub fn for_device(instance: &Instance, physical: vk::PhysicalDevice) -> Self {
+        let props = unsafe { instance.get_physical_device_properties(physical) };
+        let subgroup_size = match props.vendor_id {
+            // AMD, Qualcomm
+            0x1002 | 0x5143 => 64,
+            // ARM
+            0x13B5 => 16,
+            _ => 32,
+        };
+        let fit = |dimensions: u32| {
+            let mut size = 1u32;
+            while size.pow(dimensions) < subgroup_size {
+                size *= 2;
+            }
+            let limits = &props.limits;
+            while size > 1 && !fits(limits, size, dimensions) {
+                size /= 2;
+            }
+            size
+        };
+        Self {
+            size_2d: fit(2),
+            size_3d: fit(3),
+        }
+    }
+
+    fn fits(&self, limits: &vk::PhysicalDeviceLimits) -> bool {
+        fits(limits, self.size_2d, 2) && fits(limits, self.size_3d, 3)
+    }
+}
+
+/// Whether `limits` allow a workgroup with `dimensions` edges of length `size`
+fn fits(limits: &vk::PhysicalDeviceLimits, size: u32, dimensions: u32) -> bool {
+    size != 0
+        && size.pow(dimensions) <= limits.max_compute_work_group_invocations
+        && limits.max_compute_work_group_size[..dimensions as usize]
+            .iter()
+            .all(|&max| size <= max)
+}
+
+/// Device memory needed for an `Atmosphere`, in bytes
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct MemoryReport {
+    /// Memory held for the lifetime of the `Atmosphere`
+    pub persistent: vk::DeviceSize,
+    /// Memory held until precompute completes, including `persistent` and host-visible buffers
+    /// for `PendingAtmosphere` readback
+    pub peak: vk::DeviceSize,
 }
 
 // Taken from Bruneton's paper
@@ -855,6 +1605,11 @@ impl Default for Parameters {
             layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
 
             order: 4,
+            convergence_threshold: None,
+            record_timings: false,
+            retain_single_scattering: false,
+            validate_luts: false,
+            export_orders: false,
 
             transmittance_mu_size: 256,
             transmittance_r_size: 64,
@@ -865,7 +1620,6 @@ impl Default for Parameters {
             irradiance_mu_s_size: 64,
             irradiance_r_size: 16,
 
-            solar_irradiance: [1.474, 1.850, 1.91198],
             sun_angular_radius: 0.004675,
             bottom_radius: 6360.0,
             top_radius: 6420.0,
@@ -935,9 +1689,9 @@ impl Default for Parameters {
 }
 
 #[repr(C)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 struct ParamsRaw {
-    solar_irradiance: [f32; 3],
+    absorbtion_extinction: [f32; 3],
     sun_angular_radius: f32,
     rayleigh_scattering: [f32; 3],
     bottom_radius: f32,
@@ -947,7 +1701,6 @@ struct ParamsRaw {
     mie_phase_function_g: f32,
     ground_albedo: [f32; 3],
     mu_s_min: f32,
-    absorbtion_extinction: [f32; 3],
 
     transmittance_mu_size: u32,
     transmittance_r_size: u32,
@@ -966,7 +1719,7 @@ struct ParamsRaw {
 impl ParamsRaw {
     fn new(x: &Parameters) -> Self {
         Self {
-            solar_irradiance: x.solar_irradiance,
+            absorbtion_extinction: x.absorbtion_extinction,
             sun_angular_radius: x.sun_angular_radius,
             rayleigh_scattering: x.rayleigh_scattering,
             bottom_radius: x.bottom_radius,
@@ -976,7 +1729,6 @@ impl ParamsRaw {
             mie_phase_function_g: x.mie_phase_function_g,
             ground_albedo: x.ground_albedo,
             mu_s_min: x.mu_s_min,
-            absorbtion_extinction: x.absorbtion_extinction,
             transmittance_mu_size: x.transmittance_mu_size,
             transmittance_r_size: x.transmittance_r_size,
             scattering_r_size: x.scattering_r_size,
@@ -990,10 +1742,37 @@ impl ParamsRaw {
             absorbtion_density: DensityProfileRaw::new(&x.absorbtion_density),
         }
     }
+
+    /// Whether the transmittance look-up table computed for `self` is also correct for `other`
+    fn same_transmittance(&self, other: &Self) -> bool {
+        self.bottom_radius == other.bottom_radius
+            && self.top_radius == other.top_radius
+            && self.rayleigh_scattering == other.rayleigh_scattering
+            && self.rayleigh_density == other.rayleigh_density
+            && self.mie_extinction == other.mie_extinction
+            && self.mie_density == other.mie_density
+            && self.absorbtion_extinction == other.absorbtion_extinction
+            && self.absorbtion_density == other.absorbtion_density
+            && self.transmittance_mu_size == other.transmittance_mu_size
+            && self.transmittance_r_size == other.transmittance_r_size
+    }
+
+    /// Whether single scattering computed for `self` is also correct for `other`
+    ///
+    /// Everything else only affects direct irradiance and higher scattering orders.
+    fn same_single_scattering(&self, other: &Self) -> bool {
+        self.same_transmittance(other)
+            && self.mie_scattering == other.mie_scattering
+            && self.mu_s_min == other.mu_s_min
+            && self.scattering_r_size == other.scattering_r_size
+            && self.scattering_mu_size == other.scattering_mu_size
+            && self.scattering_mu_s_size == other.scattering_mu_s_size
+            && self.scattering_nu_size == other.scattering_nu_size
+    }
 }
 
 #[repr(C)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 struct DensityProfileRaw {
     layers: [DensityProfileLayerRaw; 2],
 }
@@ -1011,7 +1790,7 @@ impl DensityProfileRaw {
 
 #[repr(C)]
 #[repr(align(16))]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 struct DensityProfileLayerRaw {
     width: f32,
     exp_term: f32,
@@ -1032,6 +1811,54 @@ impl DensityProfileLayerRaw {
     }
 }
 
+/// State of the convergence pass, also providing indirect dispatch arguments for the passes of
+/// each scattering order
+///
+/// Followed by a `[f32; 2]` partial sum per convergence workgroup.
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ConvergenceRaw {
+    scattering_groups: [u32; 3],
+    order_reached: u32,
+    irradiance_groups: [u32; 3],
+    /// Workgroups that have stored their partial sums for the current order
+    groups_done: u32,
+}
+
+/// Invocations per workgroup of the convergence pass, each summing one scattering texel
+const CONVERGENCE_WORKGROUP_SIZE: u32 = 256;
+
+/// Number of workgroups the convergence pass is dispatched with
+fn convergence_groups(scattering: vk::Extent3D) -> u32 {
+    (scattering.width * scattering.height * scattering.depth).div_ceil(CONVERGENCE_WORKGROUP_SIZE)
+}
+
+/// Size of the convergence pass's storage buffer, including the partial sums
+fn convergence_size(scattering: vk::Extent3D) -> vk::DeviceSize {
+    (mem::size_of::<ConvergenceRaw>()
+        + convergence_groups(scattering) as usize * mem::size_of::<[f32; 2]>())
+        as vk::DeviceSize
+}
+
+/// Invalid texels of one kind in one look-up table, as found by the validation pass
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct InvalidRaw {
+    count: u32,
+    /// Linear index of the first texel, or `u32::MAX` if none
+    first: u32,
+    _padding: [u32; 2],
+    /// r, mu, mu_s and nu of the first texel
+    coords: [f32; 4],
+}
+
+/// Contents of the validation pass's storage buffer, by table then kind
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct LutReportRaw {
+    invalid: [[InvalidRaw; 2]; 3],
+}
+
 struct Pass {
     shader: vk::ShaderModule,
     pipeline: vk::Pipeline,
@@ -1052,21 +1879,45 @@ pub struct Atmosphere {
     scattering_extent: vk::Extent3D,
     irradiance: Image,
     irradiance_extent: vk::Extent2D,
+    directional_irradiance: Image,
+    /// Present if `Parameters::retain_single_scattering` was set
+    single_scattering: Option<SingleScattering>,
+    usage: vk::ImageUsageFlags,
+    /// Parameters the look-up tables were computed for
+    params_raw: ParamsRaw,
+    /// Layout the look-up tables were left in
+    layout: vk::ImageLayout,
+    scattering_order: u32,
     params: vk::Buffer,
-    params_mem: vk::DeviceMemory,
+    params_mem: Allocation,
+}
+
+/// Single scattering retained for use by rebuilds, in `SHADER_READ_ONLY_OPTIMAL` between them
+struct SingleScattering {
+    rayleigh: Image,
+    mie: Image,
 }
 
 impl Drop for Atmosphere {
     fn drop(&mut self) {
         let device = &*self.builder.device;
         unsafe {
-            for &image in &[&self.transmittance, &self.scattering, &self.irradiance] {
+            let mut images = vec![
+                &self.transmittance,
+                &self.scattering,
+                &self.irradiance,
+                &self.directional_irradiance,
+            ];
+            if let Some(ref x) = self.single_scattering {
+                images.extend(&[&x.rayleigh, &x.mie]);
+            }
+            for image in images {
                 device.destroy_image_view(image.view, None);
                 device.destroy_image(image.handle, None);
-                device.free_memory(image.memory, None);
+                self.builder.allocator.free(image.memory);
             }
             device.destroy_buffer(self.params, None);
-            device.free_memory(self.params_mem, None);
+            self.builder.allocator.free(self.params_mem);
             device.destroy_descriptor_pool(self.descriptor_pool, None);
         }
     }
@@ -1079,19 +1930,268 @@ impl Atmosphere {
         cmd: vk::CommandBuffer,
         atmosphere_params: &Parameters,
     ) -> PendingAtmosphere {
+        let mut incremental = Self::build_incremental(builder, atmosphere_params);
+        while !incremental.record(cmd) {}
+        incremental.finish()
+    }
+
+    /// Prepare to build an `Atmosphere` in bounded steps, e.g. spread across many frames
+    ///
+    /// No commands are recorded until `IncrementalAtmosphere::record` is called.
+    pub fn build_incremental(
+        builder: Arc<Builder>,
+        atmosphere_params: &Parameters,
+    ) -> IncrementalAtmosphere {
+        let atmosphere = unsafe { Self::new(builder, atmosphere_params) };
+        atmosphere.prepare(atmosphere_params, false, false)
+    }
+
+    /// Rebuild this atmosphere with new parameters, usable again when `cmd` is fully executed
+    ///
+    /// If `atmosphere_params` implies the same look-up table extents and usage, the existing
+    /// images, parameter buffer, and descriptor set are reused and only the temporaries needed
+    /// during precompute are allocated. Otherwise, equivalent to dropping `self` and calling
+    /// `build`. Must not be called while `self` is in use by the GPU.
+    ///
+    /// When reusing resources, passes whose inputs are unchanged are skipped: transmittance if
+    /// only parameters that don't affect extinction changed, and single scattering too if it was
+    /// retained with `Parameters::retain_single_scattering` and only `ground_albedo`,
+    /// `mie_phase_function_g`, `sun_angular_radius`, or `order` changed. Solar irradiance is
+    /// applied at render time, so changing it never requires a rebuild.
+    pub fn rebuild(
+        self,
+        cmd: vk::CommandBuffer,
+        atmosphere_params: &Parameters,
+    ) -> PendingAtmosphere {
+        let mut incremental = self.rebuild_incremental(atmosphere_params);
+        while !incremental.record(cmd) {}
+        incremental.finish()
+    }
+
+    /// Prepare to rebuild this atmosphere in bounded steps, as with `build_incremental`
+    ///
+    /// Reuses resources as `rebuild` does. Must not be called while `self` is in use by the GPU.
+    pub fn rebuild_incremental(self, atmosphere_params: &Parameters) -> IncrementalAtmosphere {
+        if !self.is_compatible(atmosphere_params) {
+            let builder = self.builder.clone();
+            drop(self);
+            return Self::build_incremental(builder, atmosphere_params);
+        }
+        let params_raw = ParamsRaw::new(atmosphere_params);
+        let reuse_transmittance = self.params_raw.same_transmittance(&params_raw);
+        let reuse_single_scattering =
+            self.single_scattering.is_some() && self.params_raw.same_single_scattering(&params_raw);
+        self.prepare(
+            atmosphere_params,
+            reuse_transmittance,
+            reuse_single_scattering,
+        )
+    }
+
+    /// Whether `atmosphere_params` can be precomputed into this atmosphere's images
+    fn is_compatible(&self, atmosphere_params: &Parameters) -> bool {
+        self.transmittance_extent == atmosphere_params.transmittance_extent()
+            && self.scattering_extent == atmosphere_params.scattering_extent()
+            && self.irradiance_extent == atmosphere_params.irradiance_extent()
+            && self.usage == atmosphere_params.usage
+            && self.single_scattering.is_some() == atmosphere_params.retain_single_scattering
+    }
+
+    /// Allocate the resources that outlive precompute
+    unsafe fn new(builder: Arc<Builder>, atmosphere_params: &Parameters) -> Self {
+        let device = &*builder.device;
+        let persistent_pool = device
+            .create_descriptor_pool(
+                &vk::DescriptorPoolCreateInfo::builder()
+                    .max_sets(1)
+                    .pool_sizes(&[
+                        vk::DescriptorPoolSize {
+                            ty: vk::DescriptorType::UNIFORM_BUFFER,
+                            descriptor_count: 1,
+                        },
+                        vk::DescriptorPoolSize {
+                            ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                            descriptor_count: 2,
+                        },
+                    ]),
+                None,
+            )
+            .unwrap();
+
+        let render_ds = device
+            .allocate_descriptor_sets(
+                &vk::DescriptorSetAllocateInfo::builder()
+                    .descriptor_pool(persistent_pool)
+                    .set_layouts(&[builder.render_ds_layout]),
+            )
+            .unwrap()
+            .into_iter()
+            .next()
+            .unwrap();
+        builder.name(render_ds, "fuzzyblue render");
+
+        let transmittance_extent = atmosphere_params.transmittance_extent();
+        let mut transmittance_info = atmosphere_params.transmittance_image_info();
+        let queue_families = [
+            builder.gfx_queue_family,
+            builder
+                .compute_queue_family
+                .unwrap_or(builder.gfx_queue_family),
+        ];
+        if queue_families[0] != queue_families[1] {
+            // Rebuilds may read it on the compute queue without ownership transfers
+            transmittance_info.sharing_mode = vk::SharingMode::CONCURRENT;
+            transmittance_info.queue_family_index_count = 2;
+            transmittance_info.p_queue_family_indices = queue_families.as_ptr();
+        }
+        let transmittance = builder.alloc_image(&transmittance_info, "fuzzyblue transmittance");
+
+        let irradiance_extent = atmosphere_params.irradiance_extent();
+        let irradiance_image_info = atmosphere_params.irradiance_image_info();
+        let irradiance = builder.alloc_image(&irradiance_image_info, "fuzzyblue irradiance");
+        let directional_irradiance = builder.alloc_image(
+            &atmosphere_params.directional_irradiance_image_info(),
+            "fuzzyblue directional irradiance",
+        );
+
+        let scattering_extent = atmosphere_params.scattering_extent();
+        let scattering_image_info = atmosphere_params.scattering_image_info();
+        let scattering = builder.alloc_image(&scattering_image_info, "fuzzyblue scattering");
+        let single_scattering = if atmosphere_params.retain_single_scattering {
+            Some(SingleScattering {
+                rayleigh: builder.alloc_image(
+                    &scattering_image_info,
+                    "fuzzyblue single rayleigh scattering",
+                ),
+                mie: builder.alloc_image(&scattering_image_info, "fuzzyblue single mie scattering"),
+            })
+        } else {
+            None
+        };
+
+        let params = device
+            .create_buffer(
+                &vk::BufferCreateInfo {
+                    size: mem::size_of::<ParamsRaw>() as vk::DeviceSize,
+                    usage: vk::BufferUsageFlags::UNIFORM_BUFFER
+                        | vk::BufferUsageFlags::TRANSFER_DST,
+                    ..Default::default()
+                },
+                None,
+            )
+            .unwrap();
+        let params_mem = builder.allocator.allocate(
+            device.get_buffer_memory_requirements(params),
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        );
+        device
+            .bind_buffer_memory(params, params_mem.memory, params_mem.offset)
+            .unwrap();
+        builder.name(params, "fuzzyblue params");
+
+        device.update_descriptor_sets(
+            &[
+                vk::WriteDescriptorSet {
+                    dst_set: render_ds,
+                    dst_binding: 0,
+                    dst_array_element: 0,
+                    descriptor_count: 1,
+                    descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                    p_buffer_info: &vk::DescriptorBufferInfo {
+                        buffer: params,
+                        offset: 0,
+                        range: vk::WHOLE_SIZE,
+                    },
+                    ..Default::default()
+                },
+                vk::WriteDescriptorSet {
+                    dst_set: render_ds,
+                    dst_binding: 1,
+                    dst_array_element: 0,
+                    descriptor_count: 1,
+                    descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    p_image_info: &vk::DescriptorImageInfo {
+                        sampler: vk::Sampler::null(),
+                        image_view: transmittance.view,
+                        image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    },
+                    ..Default::default()
+                },
+                vk::WriteDescriptorSet {
+                    dst_set: render_ds,
+                    dst_binding: 2,
+                    dst_array_element: 0,
+                    descriptor_count: 1,
+                    descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    p_image_info: &vk::DescriptorImageInfo {
+                        sampler: vk::Sampler::null(),
+                        image_view: scattering.view,
+                        image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    },
+                    ..Default::default()
+                },
+            ],
+            &[],
+        );
+
+        Self {
+            builder,
+            descriptor_pool: persistent_pool,
+            ds: render_ds,
+            transmittance,
+            transmittance_extent,
+            scattering,
+            scattering_extent,
+            irradiance,
+            irradiance_extent,
+            directional_irradiance,
+            single_scattering,
+            usage: atmosphere_params.usage,
+            params_raw: ParamsRaw::new(atmosphere_params),
+            layout: vk::ImageLayout::UNDEFINED,
+            scattering_order: 0,
+            params,
+            params_mem,
+        }
+    }
+
+    /// Allocate the temporaries needed to precompute `atmosphere_params` into `self`
+    ///
+    /// `reuse_transmittance` and `reuse_single_scattering` skip recomputing results already held
+    /// by `self`.
+    fn prepare(
+        mut self,
+        atmosphere_params: &Parameters,
+        reuse_transmittance: bool,
+        reuse_single_scattering: bool,
+    ) -> IncrementalAtmosphere {
+        let previous_layout = mem::replace(&mut self.layout, atmosphere_params.layout);
+        self.params_raw = ParamsRaw::new(atmosphere_params);
+        // Read back from the GPU when adaptive
+        self.scattering_order = atmosphere_params.order.max(1);
+        let builder = self.builder.clone();
         let device = &*builder.device;
+        let transmittance = &self.transmittance;
+        let scattering = &self.scattering;
+        let irradiance = &self.irradiance;
+        let directional_irradiance = &self.directional_irradiance;
+        let params = self.params;
+        let irradiance_image_info = atmosphere_params.irradiance_image_info();
+        let scattering_image_info = atmosphere_params.scattering_image_info();
         unsafe {
             // common: 1 uniform
             // transmittance: 1 storage image
             // direct irradiance: 1 image-sampler, 1 storage image
-            // indirect irradiance: 3 image-samplers, 2 storage images
+            // indirect irradiance: 3 image-samplers, 3 storage images
             // single scattering: 1 image-sampler, 3 storage images
             // scattering density: 5 image-samplers, 1 storage image
             // multiple scattering: 2 image-samplers, 2 storage images
+            // convergence: 2 storage images, 1 storage buffer
+            // validation: 3 image-samplers, 1 storage buffer
             let descriptor_pool = device
                 .create_descriptor_pool(
                     &vk::DescriptorPoolCreateInfo::builder()
-                        .max_sets(7)
+                        .max_sets(9)
                         .pool_sizes(&[
                             vk::DescriptorPoolSize {
                                 ty: vk::DescriptorType::UNIFORM_BUFFER,
@@ -1099,11 +2199,15 @@ impl Atmosphere {
                             },
                             vk::DescriptorPoolSize {
                                 ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                                descriptor_count: 12,
+                                descriptor_count: 15,
                             },
                             vk::DescriptorPoolSize {
                                 ty: vk::DescriptorType::STORAGE_IMAGE,
-                                descriptor_count: 10,
+                                descriptor_count: 13,
+                            },
+                            vk::DescriptorPoolSize {
+                                ty: vk::DescriptorType::STORAGE_BUFFER,
+                                descriptor_count: 2,
                             },
                         ]),
                     None,
@@ -1122,6 +2226,8 @@ impl Atmosphere {
                             builder.single_scattering.ds_layout,
                             builder.scattering_density.ds_layout,
                             builder.multiple_scattering.ds_layout,
+                            builder.convergence.ds_layout,
+                            builder.validation.ds_layout,
                         ]),
                 )
                 .unwrap()
@@ -1133,128 +2239,127 @@ impl Atmosphere {
             let single_scattering_ds = descriptor_sets.next().unwrap();
             let scattering_density_ds = descriptor_sets.next().unwrap();
             let multiple_scattering_ds = descriptor_sets.next().unwrap();
+            let convergence_ds = descriptor_sets.next().unwrap();
+            let validation_ds = descriptor_sets.next().unwrap();
             debug_assert!(descriptor_sets.next().is_none());
+            for &(ds, name) in &[
+                (params_ds, "fuzzyblue params"),
+                (transmittance_ds, "fuzzyblue transmittance"),
+                (direct_irradiance_ds, "fuzzyblue direct irradiance"),
+                (indirect_irradiance_ds, "fuzzyblue indirect irradiance"),
+                (single_scattering_ds, "fuzzyblue single scattering"),
+                (scattering_density_ds, "fuzzyblue scattering density"),
+                (multiple_scattering_ds, "fuzzyblue multiple scattering"),
+                (convergence_ds, "fuzzyblue convergence"),
+                (validation_ds, "fuzzyblue validation"),
+            ] {
+                builder.name(ds, name);
+            }
 
-            let persistent_pool = device
-                .create_descriptor_pool(
-                    &vk::DescriptorPoolCreateInfo::builder()
-                        .max_sets(1)
-                        .pool_sizes(&[
-                            vk::DescriptorPoolSize {
-                                ty: vk::DescriptorType::UNIFORM_BUFFER,
-                                descriptor_count: 1,
-                            },
-                            vk::DescriptorPoolSize {
-                                ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                                descriptor_count: 2,
-                            },
-                        ]),
-                    None,
-                )
-                .unwrap();
-
-            let render_ds = device
-                .allocate_descriptor_sets(
-                    &vk::DescriptorSetAllocateInfo::builder()
-                        .descriptor_pool(persistent_pool)
-                        .set_layouts(&[builder.render_ds_layout]),
-                )
-                .unwrap()
-                .into_iter()
-                .next()
-                .unwrap();
-
-            let transmittance_extent = atmosphere_params.transmittance_extent();
-            let transmittance = builder.alloc_image(&vk::ImageCreateInfo {
-                image_type: vk::ImageType::TYPE_2D,
-                format: vk::Format::R32G32B32A32_SFLOAT,
-                extent: vk::Extent3D {
-                    width: transmittance_extent.width,
-                    height: transmittance_extent.height,
-                    depth: 1,
-                },
-                mip_levels: 1,
-                array_layers: 1,
-                samples: vk::SampleCountFlags::TYPE_1,
-                tiling: vk::ImageTiling::OPTIMAL,
-                usage: vk::ImageUsageFlags::STORAGE
-                    | vk::ImageUsageFlags::SAMPLED
-                    | atmosphere_params.usage,
-                sharing_mode: vk::SharingMode::EXCLUSIVE,
-                initial_layout: vk::ImageLayout::UNDEFINED,
-                ..Default::default()
-            });
-
-            let irradiance_extent = atmosphere_params.irradiance_extent();
-            let irradiance_image_info = vk::ImageCreateInfo {
-                image_type: vk::ImageType::TYPE_2D,
-                format: vk::Format::R32G32B32A32_SFLOAT,
-                extent: vk::Extent3D {
-                    width: irradiance_extent.width,
-                    height: irradiance_extent.height,
-                    depth: 1,
-                },
-                mip_levels: 1,
-                array_layers: 1,
-                samples: vk::SampleCountFlags::TYPE_1,
-                tiling: vk::ImageTiling::OPTIMAL,
-                usage: vk::ImageUsageFlags::STORAGE
-                    | vk::ImageUsageFlags::SAMPLED
-                    | vk::ImageUsageFlags::TRANSFER_DST
-                    | atmosphere_params.usage,
-                sharing_mode: vk::SharingMode::EXCLUSIVE,
-                initial_layout: vk::ImageLayout::UNDEFINED,
-                ..Default::default()
-            };
-            let delta_irradiance = builder.alloc_image(&irradiance_image_info);
-            let irradiance = builder.alloc_image(&irradiance_image_info);
-
-            let scattering_extent = atmosphere_params.scattering_extent();
-            let scattering_image_info = vk::ImageCreateInfo {
-                image_type: vk::ImageType::TYPE_3D,
-                format: vk::Format::R16G16B16A16_SFLOAT,
-                extent: scattering_extent,
-                mip_levels: 1,
-                array_layers: 1,
-                samples: vk::SampleCountFlags::TYPE_1,
-                tiling: vk::ImageTiling::OPTIMAL,
-                usage: vk::ImageUsageFlags::STORAGE
-                    | vk::ImageUsageFlags::SAMPLED
-                    | atmosphere_params.usage,
-                sharing_mode: vk::SharingMode::EXCLUSIVE,
-                initial_layout: vk::ImageLayout::UNDEFINED,
-                ..Default::default()
+            // Temporaries share one allocation
+            let temporary_infos = atmosphere_params.temporary_image_infos();
+            let temporary_handles = temporary_infos
+                .iter()
+                .map(|info| device.create_image(info, None).unwrap())
+                .collect::<Vec<_>>();
+            let (offsets, temporary_reqs) = pack(
+                &temporary_handles
+                    .iter()
+                    .map(|&image| device.get_image_memory_requirements(image))
+                    .collect::<Vec<_>>(),
+            );
+            let temporary_memory = builder
+                .allocator
+                .allocate(temporary_reqs, vk::MemoryPropertyFlags::DEVICE_LOCAL);
+            for (&image, &offset) in temporary_handles.iter().zip(&offsets) {
+                device
+                    .bind_image_memory(
+                        image,
+                        temporary_memory.memory,
+                        temporary_memory.offset + offset,
+                    )
+                    .unwrap();
+            }
+            let temporary = |handle, info: &vk::ImageCreateInfo, name: &str| {
+                let view = builder.create_view(handle, info);
+                builder.name(handle, name);
+                builder.name(view, &format!("{} view", name));
+                Image {
+                    handle,
+                    view,
+                    memory: temporary_memory,
+                }
             };
-            // TODO: These could be merged
-            let delta_rayleigh = builder.alloc_image(&scattering_image_info);
-            let delta_mie = builder.alloc_image(&scattering_image_info);
-            let scattering = builder.alloc_image(&scattering_image_info);
-            // TODO: This could overlap with delta_rayleigh/mie, since they are not used simultaneously
-            let delta_multiple_scattering = builder.alloc_image(&scattering_image_info);
-            let scattering_density = builder.alloc_image(&scattering_image_info);
-
-            let params = device
-                .create_buffer(
-                    &vk::BufferCreateInfo {
-                        size: mem::size_of::<ParamsRaw>() as vk::DeviceSize,
-                        usage: vk::BufferUsageFlags::UNIFORM_BUFFER
-                            | vk::BufferUsageFlags::TRANSFER_DST,
-                        ..Default::default()
-                    },
+            let delta_irradiance = temporary(
+                temporary_handles[0],
+                &irradiance_image_info,
+                "fuzzyblue delta irradiance",
+            );
+            let scattering_density = temporary(
+                temporary_handles[2],
+                &scattering_image_info,
+                "fuzzyblue scattering density",
+            );
+            let (single_scattering, delta_multiple_scattering) = match self.single_scattering {
+                Some(_) => (
                     None,
-                )
-                .unwrap();
-            let params_mem = {
-                let reqs = device.get_buffer_memory_requirements(params);
-                allocate(
-                    device,
-                    &builder.memory_props,
-                    reqs,
-                    vk::MemoryPropertyFlags::DEVICE_LOCAL,
-                )
-                .unwrap()
+                    temporary(
+                        temporary_handles[1],
+                        &scattering_image_info,
+                        "fuzzyblue delta multiple scattering",
+                    ),
+                ),
+                None => {
+                    // delta_multiple_scattering is first written by the second order's multiple
+                    // scattering pass, after the last read of delta_rayleigh, so they can overlap.
+                    let delta_multiple_scattering_handle =
+                        device.create_image(&temporary_infos[1], None).unwrap();
+                    let reqs =
+                        device.get_image_memory_requirements(delta_multiple_scattering_handle);
+                    let slot = device.get_image_memory_requirements(temporary_handles[1]);
+                    assert!(
+                        reqs.size <= slot.size
+                            && offsets[1] % reqs.alignment == 0
+                            && reqs.memory_type_bits & temporary_reqs.memory_type_bits
+                                == temporary_reqs.memory_type_bits,
+                        "delta_multiple_scattering doesn't fit delta_rayleigh's memory"
+                    );
+                    device
+                        .bind_image_memory(
+                            delta_multiple_scattering_handle,
+                            temporary_memory.memory,
+                            temporary_memory.offset + offsets[1],
+                        )
+                        .unwrap();
+                    (
+                        Some(SingleScattering {
+                            rayleigh: temporary(
+                                temporary_handles[1],
+                                &scattering_image_info,
+                                "fuzzyblue delta rayleigh scattering",
+                            ),
+                            mie: temporary(
+                                temporary_handles[3],
+                                &scattering_image_info,
+                                "fuzzyblue delta mie scattering",
+                            ),
+                        }),
+                        temporary(
+                            delta_multiple_scattering_handle,
+                            &scattering_image_info,
+                            "fuzzyblue delta multiple scattering",
+                        ),
+                    )
+                }
+            };
+            let (delta_rayleigh, delta_mie) = {
+                let x = self
+                    .single_scattering
+                    .as_ref()
+                    .or(single_scattering.as_ref())
+                    .unwrap();
+                (&x.rayleigh, &x.mie)
             };
-            device.bind_buffer_memory(params, params_mem, 0).unwrap();
 
             device.update_descriptor_sets(
                 &[
@@ -1410,7 +2515,7 @@ impl Atmosphere {
                         p_image_info: &vk::DescriptorImageInfo {
                             sampler: vk::Sampler::null(),
                             image_view: delta_multiple_scattering.view,
-                            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                            image_layout: vk::ImageLayout::GENERAL,
                         },
                         ..Default::default()
                     },
@@ -1475,7 +2580,7 @@ impl Atmosphere {
                         p_image_info: &vk::DescriptorImageInfo {
                             sampler: vk::Sampler::null(),
                             image_view: delta_multiple_scattering.view,
-                            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                            image_layout: vk::ImageLayout::GENERAL,
                         },
                         ..Default::default()
                     },
@@ -1506,11 +2611,24 @@ impl Atmosphere {
                         ..Default::default()
                     },
                     vk::WriteDescriptorSet {
-                        dst_set: multiple_scattering_ds,
-                        dst_binding: 0,
+                        dst_set: indirect_irradiance_ds,
+                        dst_binding: 5,
                         dst_array_element: 0,
                         descriptor_count: 1,
-                        descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                        descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                        p_image_info: &vk::DescriptorImageInfo {
+                            sampler: vk::Sampler::null(),
+                            image_view: directional_irradiance.view,
+                            image_layout: vk::ImageLayout::GENERAL,
+                        },
+                        ..Default::default()
+                    },
+                    vk::WriteDescriptorSet {
+                        dst_set: multiple_scattering_ds,
+                        dst_binding: 0,
+                        dst_array_element: 0,
+                        descriptor_count: 1,
+                        descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
                         p_image_info: &vk::DescriptorImageInfo {
                             sampler: vk::Sampler::null(),
                             image_view: transmittance.view,
@@ -1557,49 +2675,439 @@ impl Atmosphere {
                         },
                         ..Default::default()
                     },
-                    vk::WriteDescriptorSet {
-                        dst_set: render_ds,
-                        dst_binding: 0,
-                        dst_array_element: 0,
-                        descriptor_count: 1,
-                        descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
-                        p_buffer_info: &vk::DescriptorBufferInfo {
-                            buffer: params,
-                            offset: 0,
-                            range: vk::WHOLE_SIZE,
+                ],
+                &[],
+            );
+
+            let convergence = if atmosphere_params.convergence_threshold.is_some() {
+                // Host-visible for readback of the order reached
+                let buffer = device
+                    .create_buffer(
+                        &vk::BufferCreateInfo {
+                            size: convergence_size(atmosphere_params.scattering_extent()),
+                            usage: vk::BufferUsageFlags::STORAGE_BUFFER
+                                | vk::BufferUsageFlags::INDIRECT_BUFFER
+                                | vk::BufferUsageFlags::TRANSFER_DST,
+                            ..Default::default()
                         },
-                        ..Default::default()
-                    },
-                    vk::WriteDescriptorSet {
-                        dst_set: render_ds,
-                        dst_binding: 1,
-                        dst_array_element: 0,
-                        descriptor_count: 1,
-                        descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                        p_image_info: &vk::DescriptorImageInfo {
-                            sampler: vk::Sampler::null(),
-                            image_view: transmittance.view,
-                            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                        None,
+                    )
+                    .unwrap();
+                let memory = builder.allocator.allocate(
+                    device.get_buffer_memory_requirements(buffer),
+                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                );
+                device
+                    .bind_buffer_memory(buffer, memory.memory, memory.offset)
+                    .unwrap();
+                device.update_descriptor_sets(
+                    &[
+                        vk::WriteDescriptorSet {
+                            dst_set: convergence_ds,
+                            dst_binding: 0,
+                            dst_array_element: 0,
+                            descriptor_count: 1,
+                            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                            p_image_info: &vk::DescriptorImageInfo {
+                                sampler: vk::Sampler::null(),
+                                image_view: delta_multiple_scattering.view,
+                                image_layout: vk::ImageLayout::GENERAL,
+                            },
+                            ..Default::default()
                         },
-                        ..Default::default()
-                    },
-                    vk::WriteDescriptorSet {
-                        dst_set: render_ds,
-                        dst_binding: 2,
-                        dst_array_element: 0,
-                        descriptor_count: 1,
-                        descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                        p_image_info: &vk::DescriptorImageInfo {
-                            sampler: vk::Sampler::null(),
-                            image_view: scattering.view,
-                            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                        vk::WriteDescriptorSet {
+                            dst_set: convergence_ds,
+                            dst_binding: 1,
+                            dst_array_element: 0,
+                            descriptor_count: 1,
+                            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                            p_image_info: &vk::DescriptorImageInfo {
+                                sampler: vk::Sampler::null(),
+                                image_view: scattering.view,
+                                image_layout: vk::ImageLayout::GENERAL,
+                            },
+                            ..Default::default()
                         },
-                        ..Default::default()
+                        vk::WriteDescriptorSet {
+                            dst_set: convergence_ds,
+                            dst_binding: 2,
+                            dst_array_element: 0,
+                            descriptor_count: 1,
+                            descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                            p_buffer_info: &vk::DescriptorBufferInfo {
+                                buffer,
+                                offset: 0,
+                                range: vk::WHOLE_SIZE,
+                            },
+                            ..Default::default()
+                        },
+                    ],
+                    &[],
+                );
+                Some(Convergence { buffer, memory })
+            } else {
+                None
+            };
+
+            let validation = if atmosphere_params.validate_luts {
+                // Host-visible for readback of the report
+                let buffer = device
+                    .create_buffer(
+                        &vk::BufferCreateInfo {
+                            size: mem::size_of::<LutReportRaw>() as vk::DeviceSize,
+                            usage: vk::BufferUsageFlags::STORAGE_BUFFER
+                                | vk::BufferUsageFlags::TRANSFER_DST,
+                            ..Default::default()
+                        },
+                        None,
+                    )
+                    .unwrap();
+                let memory = builder.allocator.allocate(
+                    device.get_buffer_memory_requirements(buffer),
+                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                );
+                device
+                    .bind_buffer_memory(buffer, memory.memory, memory.offset)
+                    .unwrap();
+                device.update_descriptor_sets(
+                    &[
+                        vk::WriteDescriptorSet {
+                            dst_set: validation_ds,
+                            dst_binding: 0,
+                            dst_array_element: 0,
+                            descriptor_count: 1,
+                            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                            p_image_info: &vk::DescriptorImageInfo {
+                                sampler: vk::Sampler::null(),
+                                image_view: transmittance.view,
+                                image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                            },
+                            ..Default::default()
+                        },
+                        // Still in use as storage images when validated
+                        vk::WriteDescriptorSet {
+                            dst_set: validation_ds,
+                            dst_binding: 1,
+                            dst_array_element: 0,
+                            descriptor_count: 1,
+                            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                            p_image_info: &vk::DescriptorImageInfo {
+                                sampler: vk::Sampler::null(),
+                                image_view: irradiance.view,
+                                image_layout: vk::ImageLayout::GENERAL,
+                            },
+                            ..Default::default()
+                        },
+                        vk::WriteDescriptorSet {
+                            dst_set: validation_ds,
+                            dst_binding: 2,
+                            dst_array_element: 0,
+                            descriptor_count: 1,
+                            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                            p_image_info: &vk::DescriptorImageInfo {
+                                sampler: vk::Sampler::null(),
+                                image_view: scattering.view,
+                                image_layout: vk::ImageLayout::GENERAL,
+                            },
+                            ..Default::default()
+                        },
+                        vk::WriteDescriptorSet {
+                            dst_set: validation_ds,
+                            dst_binding: 3,
+                            dst_array_element: 0,
+                            descriptor_count: 1,
+                            descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                            p_buffer_info: &vk::DescriptorBufferInfo {
+                                buffer,
+                                offset: 0,
+                                range: vk::WHOLE_SIZE,
+                            },
+                            ..Default::default()
+                        },
+                    ],
+                    &[],
+                );
+                Some(Validation { buffer, memory })
+            } else {
+                None
+            };
+
+            let exports = if atmosphere_params.export_orders {
+                let size = export_offsets(
+                    atmosphere_params.irradiance_extent(),
+                    atmosphere_params.scattering_extent(),
+                )[2];
+                (0..atmosphere_params.order.max(1))
+                    .map(|_| {
+                        let buffer = device
+                            .create_buffer(
+                                &vk::BufferCreateInfo {
+                                    size,
+                                    usage: vk::BufferUsageFlags::TRANSFER_DST,
+                                    ..Default::default()
+                                },
+                                None,
+                            )
+                            .unwrap();
+                        let memory = builder.allocator.allocate(
+                            device.get_buffer_memory_requirements(buffer),
+                            vk::MemoryPropertyFlags::HOST_VISIBLE
+                                | vk::MemoryPropertyFlags::HOST_COHERENT,
+                        );
+                        device
+                            .bind_buffer_memory(buffer, memory.memory, memory.offset)
+                            .unwrap();
+                        Export { buffer, memory }
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+
+            let timestamps = if atmosphere_params.record_timings {
+                // One before the first step, and one after each
+                let count = step_count(atmosphere_params.order) + 1;
+                let pool = device
+                    .create_query_pool(
+                        &vk::QueryPoolCreateInfo {
+                            query_type: vk::QueryType::TIMESTAMP,
+                            query_count: count,
+                            ..Default::default()
+                        },
+                        None,
+                    )
+                    .unwrap();
+                Some(Timestamps { pool, count })
+            } else {
+                None
+            };
+
+            let pending = PendingAtmosphere {
+                device: builder.device.clone(),
+                allocator: builder.allocator.clone(),
+                descriptor_pool,
+                inner: Some(self),
+                temporary_memory,
+                delta_irradiance,
+                single_scattering,
+                scattering_density,
+                delta_multiple_scattering,
+                convergence,
+                validation,
+                exports,
+                timestamps,
+            };
+
+            IncrementalAtmosphere {
+                pending,
+                params_ds,
+                transmittance_ds,
+                direct_irradiance_ds,
+                indirect_irradiance_ds,
+                single_scattering_ds,
+                scattering_density_ds,
+                multiple_scattering_ds,
+                convergence_ds,
+                validation_ds,
+                params_raw: ParamsRaw::new(atmosphere_params),
+                order: atmosphere_params.order,
+                convergence_threshold: atmosphere_params.convergence_threshold,
+                dst_stage_mask: atmosphere_params.dst_stage_mask,
+                dst_access_mask: atmosphere_params.dst_access_mask,
+                layout: atmosphere_params.layout,
+                previous_layout,
+                reuse_transmittance,
+                reuse_single_scattering,
+                step: Step::Transmittance,
+                recorded: 0,
+            }
+        }
+    }
+
+    pub fn transmittance(&self) -> vk::Image {
+        self.transmittance.handle
+    }
+    pub fn transmittance_view(&self) -> vk::ImageView {
+        self.transmittance.view
+    }
+    pub fn transmittance_extent(&self) -> vk::Extent2D {
+        self.transmittance_extent
+    }
+    pub fn scattering(&self) -> vk::Image {
+        self.scattering.handle
+    }
+    pub fn scattering_view(&self) -> vk::ImageView {
+        self.scattering.view
+    }
+    pub fn scattering_extent(&self) -> vk::Extent3D {
+        self.scattering_extent
+    }
+    pub fn irradiance(&self) -> vk::Image {
+        self.irradiance.handle
+    }
+    pub fn irradiance_view(&self) -> vk::ImageView {
+        self.irradiance.view
+    }
+    pub fn irradiance_extent(&self) -> vk::Extent2D {
+        self.irradiance_extent
+    }
+    /// Sky irradiance on arbitrarily oriented surfaces, used with `irradiance` by
+    /// `GetSunAndSkyIrradiance`
+    ///
+    /// A three-layer array with the same extent and parameterization as `irradiance`, holding a
+    /// first-order spherical harmonic approximation in a frame aligned with the sun's azimuth.
+    pub fn directional_irradiance(&self) -> vk::Image {
+        self.directional_irradiance.handle
+    }
+    pub fn directional_irradiance_view(&self) -> vk::ImageView {
+        self.directional_irradiance.view
+    }
+
+    /// Number of light bounces simulated, which may be less than `Parameters::order` if
+    /// `Parameters::convergence_threshold` was set
+    pub fn scattering_order(&self) -> u32 {
+        self.scattering_order
+    }
+
+    pub(crate) fn descriptor_set(&self) -> vk::DescriptorSet {
+        self.ds
+    }
+}
+
+/// An atmosphere whose precompute is recorded a step at a time
+///
+/// Each step is a single pass over one look-up table, bounding the GPU time spent per call to
+/// `record`. Steps must execute in the order they're recorded, e.g. by submitting every command
+/// buffer to the same queue in order. To cancel, drop this once every command buffer passed to
+/// `record` has completed execution.
+pub struct IncrementalAtmosphere {
+    pending: PendingAtmosphere,
+    params_ds: vk::DescriptorSet,
+    transmittance_ds: vk::DescriptorSet,
+    direct_irradiance_ds: vk::DescriptorSet,
+    indirect_irradiance_ds: vk::DescriptorSet,
+    single_scattering_ds: vk::DescriptorSet,
+    scattering_density_ds: vk::DescriptorSet,
+    multiple_scattering_ds: vk::DescriptorSet,
+    convergence_ds: vk::DescriptorSet,
+    validation_ds: vk::DescriptorSet,
+    params_raw: ParamsRaw,
+    order: u32,
+    convergence_threshold: Option<f32>,
+    dst_stage_mask: vk::PipelineStageFlags,
+    dst_access_mask: vk::AccessFlags,
+    layout: vk::ImageLayout,
+    /// Layout the look-up tables were left in by the previous build, if rebuilding
+    previous_layout: vk::ImageLayout,
+    /// Whether the atmosphere's transmittance is already correct
+    reuse_transmittance: bool,
+    /// Whether the atmosphere's retained single scattering is already correct
+    reuse_single_scattering: bool,
+    step: Step,
+    recorded: u32,
+}
+
+impl IncrementalAtmosphere {
+    /// Record the next step into `cmd`, returning whether every step has now been recorded
+    ///
+    /// May be called repeatedly with the same `cmd` to record more work at once.
+    pub fn record(&mut self, cmd: vk::CommandBuffer) -> bool {
+        let pending = &self.pending;
+        let atmosphere = pending.inner.as_ref().unwrap();
+        let builder = &*atmosphere.builder;
+        let device = &*builder.device;
+        let (transmittance, scattering, irradiance, directional_irradiance) = (
+            &atmosphere.transmittance,
+            &atmosphere.scattering,
+            &atmosphere.irradiance,
+            &atmosphere.directional_irradiance,
+        );
+        let single_scattering = atmosphere
+            .single_scattering
+            .as_ref()
+            .or(pending.single_scattering.as_ref())
+            .unwrap();
+        let (delta_irradiance, delta_rayleigh, delta_mie) = (
+            &pending.delta_irradiance,
+            &single_scattering.rayleigh,
+            &single_scattering.mie,
+        );
+        let (scattering_density, delta_multiple_scattering) = (
+            &pending.scattering_density,
+            &pending.delta_multiple_scattering,
+        );
+        let params = atmosphere.params;
+        let transmittance_extent = atmosphere.transmittance_extent;
+        let scattering_extent = atmosphere.scattering_extent;
+        let irradiance_extent = atmosphere.irradiance_extent;
+        let params_ds = self.params_ds;
+        let transmittance_ds = self.transmittance_ds;
+        let direct_irradiance_ds = self.direct_irradiance_ds;
+        let indirect_irradiance_ds = self.indirect_irradiance_ds;
+        let single_scattering_ds = self.single_scattering_ds;
+        let scattering_density_ds = self.scattering_density_ds;
+        let multiple_scattering_ds = self.multiple_scattering_ds;
+        let convergence_ds = self.convergence_ds;
+        let convergence = pending.convergence.as_ref().map(|x| x.buffer);
+        let validation_ds = self.validation_ds;
+        let validation = pending.validation.as_ref().map(|x| x.buffer);
+        let WorkgroupSizes { size_2d, size_3d } = builder.workgroup_sizes;
+        let scattering_groups = [
+            scattering_extent.width.div_ceil(size_3d),
+            scattering_extent.height.div_ceil(size_3d),
+            scattering_extent.depth.div_ceil(size_3d),
+        ];
+        let irradiance_groups = [
+            irradiance_extent.width.div_ceil(size_2d),
+            irradiance_extent.height.div_ceil(size_2d),
+            1,
+        ];
+        // Past convergence, the passes of further orders are dispatched with zero workgroups
+        let dispatch_order = |groups: [u32; 3], offset| unsafe {
+            match convergence {
+                Some(buffer) => device.cmd_dispatch_indirect(cmd, buffer, offset),
+                None => device.cmd_dispatch(cmd, groups[0], groups[1], groups[2]),
+            }
+        };
+        // Copy delta irradiance and two scattering-sized tables into an order's export
+        let export_order = |buffer, images: [(vk::Image, vk::ImageLayout); 3]| unsafe {
+            let offsets = export_offsets(irradiance_extent, scattering_extent);
+            let regions = [
+                (
+                    0,
+                    vk::Extent3D {
+                        width: irradiance_extent.width,
+                        height: irradiance_extent.height,
+                        depth: 1,
                     },
-                ],
-                &[],
-            );
+                ),
+                (offsets[0], scattering_extent),
+                (offsets[1], scattering_extent),
+            ];
+            for (&(image, layout), &(buffer_offset, image_extent)) in images.iter().zip(&regions) {
+                device.cmd_copy_image_to_buffer(
+                    cmd,
+                    image,
+                    layout,
+                    buffer,
+                    &[vk::BufferImageCopy {
+                        buffer_offset,
+                        buffer_row_length: 0,
+                        buffer_image_height: 0,
+                        image_subresource: vk::ImageSubresourceLayers {
+                            aspect_mask: vk::ImageAspectFlags::COLOR,
+                            mip_level: 0,
+                            base_array_layer: 0,
+                            layer_count: 1,
+                        },
+                        image_offset: vk::Offset3D::default(),
+                        image_extent,
+                    }],
+                );
+            }
+        };
 
+        unsafe {
             let init_barrier = vk::ImageMemoryBarrier {
                 dst_access_mask: vk::AccessFlags::SHADER_WRITE,
                 old_layout: vk::ImageLayout::UNDEFINED,
@@ -1663,445 +3171,947 @@ impl Atmosphere {
                 },
                 ..Default::default()
             };
+            let directional_irradiance_range = vk::ImageSubresourceRange {
+                layer_count: DIRECTIONAL_IRRADIANCE_LAYERS,
+                ..init_barrier.subresource_range
+            };
 
-            //
-            // Write commands
-            //
-
-            device.cmd_update_buffer(
-                cmd,
-                params,
-                0,
-                &mem::transmute::<_, [u8; 320]>(ParamsRaw::new(atmosphere_params)),
-            );
-            device.cmd_pipeline_barrier(
-                cmd,
-                vk::PipelineStageFlags::TRANSFER,
-                vk::PipelineStageFlags::COMPUTE_SHADER,
-                Default::default(),
-                &[],
-                &[vk::BufferMemoryBarrier {
-                    src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
-                    dst_access_mask: vk::AccessFlags::UNIFORM_READ,
-                    src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
-                    dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
-                    buffer: params,
-                    offset: 0,
-                    size: vk::WHOLE_SIZE,
-                    ..Default::default()
-                }],
-                &[
-                    vk::ImageMemoryBarrier {
-                        image: transmittance.handle,
-                        ..init_barrier
-                    },
-                    vk::ImageMemoryBarrier {
-                        image: delta_rayleigh.handle,
-                        ..init_barrier
-                    },
-                    vk::ImageMemoryBarrier {
-                        image: delta_mie.handle,
-                        ..init_barrier
-                    },
-                    vk::ImageMemoryBarrier {
-                        image: scattering.handle,
-                        ..init_barrier
-                    },
-                    vk::ImageMemoryBarrier {
-                        image: irradiance.handle,
-                        new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-                        ..init_barrier
-                    },
-                    vk::ImageMemoryBarrier {
-                        image: delta_irradiance.handle,
-                        ..init_barrier
-                    },
-                    vk::ImageMemoryBarrier {
-                        image: delta_multiple_scattering.handle,
-                        ..init_barrier
-                    },
-                ],
-            );
-
-            // Transmittance
-            device.cmd_bind_pipeline(
-                cmd,
-                vk::PipelineBindPoint::COMPUTE,
-                builder.transmittance.pipeline,
-            );
-            device.cmd_bind_descriptor_sets(
-                cmd,
-                vk::PipelineBindPoint::COMPUTE,
-                builder.transmittance.layout,
-                0,
-                &[params_ds, transmittance_ds],
-                &[],
-            );
-            device.cmd_dispatch(
-                cmd,
-                transmittance_extent.width / WORKGROUP_SIZE_2D,
-                transmittance_extent.height / WORKGROUP_SIZE_2D,
-                1,
-            );
-
-            device.cmd_pipeline_barrier(
-                cmd,
-                vk::PipelineStageFlags::COMPUTE_SHADER,
-                vk::PipelineStageFlags::COMPUTE_SHADER,
-                Default::default(),
-                &[],
-                &[],
-                &[vk::ImageMemoryBarrier {
-                    image: transmittance.handle,
-                    ..write_read_barrier
-                }],
-            );
-
-            // Direct irradiance
-            device.cmd_bind_pipeline(
-                cmd,
-                vk::PipelineBindPoint::COMPUTE,
-                builder.direct_irradiance.pipeline,
-            );
-            device.cmd_bind_descriptor_sets(
-                cmd,
-                vk::PipelineBindPoint::COMPUTE,
-                builder.direct_irradiance.layout,
-                1,
-                &[direct_irradiance_ds],
-                &[],
-            );
-            device.cmd_dispatch(
-                cmd,
-                irradiance_extent.width / WORKGROUP_SIZE_2D,
-                irradiance_extent.height / WORKGROUP_SIZE_2D,
-                1,
-            );
-
-            // Single scattering
-            device.cmd_bind_pipeline(
-                cmd,
-                vk::PipelineBindPoint::COMPUTE,
-                builder.single_scattering.pipeline,
-            );
-            device.cmd_bind_descriptor_sets(
-                cmd,
-                vk::PipelineBindPoint::COMPUTE,
-                builder.single_scattering.layout,
-                1,
-                &[single_scattering_ds],
-                &[],
-            );
-            device.cmd_dispatch(
-                cmd,
-                scattering_extent.width / WORKGROUP_SIZE_3D,
-                scattering_extent.height / WORKGROUP_SIZE_3D,
-                scattering_extent.depth / WORKGROUP_SIZE_3D,
-            );
-
-            device.cmd_clear_color_image(
-                cmd,
-                irradiance.handle,
-                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-                &vk::ClearColorValue {
-                    float32: [0.0, 0.0, 0.0, 0.0],
-                },
-                &[vk::ImageSubresourceRange {
-                    aspect_mask: vk::ImageAspectFlags::COLOR,
-                    base_mip_level: 0,
-                    level_count: 1,
-                    base_array_layer: 0,
-                    layer_count: 1,
-                }],
-            );
-
-            device.cmd_pipeline_barrier(
-                cmd,
-                vk::PipelineStageFlags::TRANSFER,
-                vk::PipelineStageFlags::COMPUTE_SHADER,
-                Default::default(),
-                &[],
-                &[],
-                &[vk::ImageMemoryBarrier {
-                    image: irradiance.handle,
-                    src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
-                    old_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-                    ..write_barrier
-                }],
-            );
-
-            device.cmd_pipeline_barrier(
-                cmd,
-                vk::PipelineStageFlags::COMPUTE_SHADER,
-                vk::PipelineStageFlags::COMPUTE_SHADER,
-                Default::default(),
-                &[],
-                &[],
-                &[
-                    vk::ImageMemoryBarrier {
-                        image: delta_rayleigh.handle,
-                        ..write_read_barrier
-                    },
-                    vk::ImageMemoryBarrier {
-                        image: delta_mie.handle,
-                        ..write_read_barrier
-                    },
-                ],
-            );
-
-            // Compute higher-order effects
-            for order in 2..=atmosphere_params.order {
-                device.cmd_pipeline_barrier(
-                    cmd,
-                    vk::PipelineStageFlags::COMPUTE_SHADER,
-                    vk::PipelineStageFlags::COMPUTE_SHADER,
-                    Default::default(),
-                    &[],
-                    &[],
-                    &[
+            let debug = builder.debug().filter(|_| self.step != Step::Done);
+            if let Some(debug) = debug {
+                debug.begin_label(cmd, &self.step.label());
+            }
+            match self.step {
+                Step::Transmittance => {
+                    if let Some(ref x) = pending.timestamps {
+                        device.cmd_reset_query_pool(cmd, x.pool, 0, x.count);
+                        device.cmd_write_timestamp(
+                            cmd,
+                            vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                            x.pool,
+                            0,
+                        );
+                    }
+                    device.cmd_update_buffer(
+                        cmd,
+                        params,
+                        0,
+                        &mem::transmute::<_, [u8; 304]>(self.params_raw),
+                    );
+                    let mut barriers = vec![
                         vk::ImageMemoryBarrier {
-                            image: scattering_density.handle,
-                            src_access_mask: vk::AccessFlags::SHADER_READ,
+                            image: scattering.handle,
                             ..init_barrier
                         },
                         vk::ImageMemoryBarrier {
-                            image: delta_irradiance.handle,
-                            ..write_read_barrier
+                            image: irradiance.handle,
+                            new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                            ..init_barrier
                         },
                         vk::ImageMemoryBarrier {
-                            image: delta_multiple_scattering.handle,
-                            ..write_read_barrier
+                            image: directional_irradiance.handle,
+                            new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                            subresource_range: directional_irradiance_range,
+                            ..init_barrier
                         },
-                    ],
-                );
-
-                // Scattering density
-                device.cmd_bind_pipeline(
-                    cmd,
-                    vk::PipelineBindPoint::COMPUTE,
-                    builder.scattering_density.pipeline,
-                );
-                device.cmd_bind_descriptor_sets(
-                    cmd,
-                    vk::PipelineBindPoint::COMPUTE,
-                    builder.scattering_density.layout,
-                    0,
-                    &[params_ds, scattering_density_ds],
-                    &[],
-                );
-                device.cmd_push_constants(
-                    cmd,
-                    builder.scattering_density.layout,
-                    vk::ShaderStageFlags::COMPUTE,
-                    0,
-                    &order.to_ne_bytes(),
-                );
-                device.cmd_dispatch(
-                    cmd,
-                    scattering_extent.width / WORKGROUP_SIZE_3D,
-                    scattering_extent.height / WORKGROUP_SIZE_3D,
-                    scattering_extent.depth / WORKGROUP_SIZE_3D,
-                );
-
-                device.cmd_pipeline_barrier(
-                    cmd,
-                    vk::PipelineStageFlags::COMPUTE_SHADER,
-                    vk::PipelineStageFlags::COMPUTE_SHADER,
-                    Default::default(),
-                    &[],
-                    &[],
-                    &[
-                        // Scattering density reads this
                         vk::ImageMemoryBarrier {
                             image: delta_irradiance.handle,
-                            ..read_write_barrier
+                            ..init_barrier
                         },
-                        // Previous irradiance pass output must be written
                         vk::ImageMemoryBarrier {
-                            image: irradiance.handle,
-                            ..write_barrier
+                            image: delta_multiple_scattering.handle,
+                            ..init_barrier
                         },
-                    ],
-                );
+                    ];
+                    if self.reuse_transmittance {
+                        // The caller guarantees that previous uses have completed, and there are
+                        // no writes to make visible
+                        barriers.push(vk::ImageMemoryBarrier {
+                            image: transmittance.handle,
+                            dst_access_mask: vk::AccessFlags::SHADER_READ,
+                            old_layout: self.previous_layout,
+                            new_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                            ..init_barrier
+                        });
+                    } else {
+                        barriers.push(vk::ImageMemoryBarrier {
+                            image: transmittance.handle,
+                            ..init_barrier
+                        });
+                    }
+                    // Retained single scattering is left in SHADER_READ_ONLY_OPTIMAL
+                    if !self.reuse_single_scattering {
+                        barriers.push(vk::ImageMemoryBarrier {
+                            image: delta_rayleigh.handle,
+                            ..init_barrier
+                        });
+                        barriers.push(vk::ImageMemoryBarrier {
+                            image: delta_mie.handle,
+                            ..init_barrier
+                        });
+                    }
+                    device.cmd_pipeline_barrier(
+                        cmd,
+                        vk::PipelineStageFlags::TRANSFER,
+                        vk::PipelineStageFlags::COMPUTE_SHADER,
+                        Default::default(),
+                        &[],
+                        &[vk::BufferMemoryBarrier {
+                            src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+                            dst_access_mask: vk::AccessFlags::UNIFORM_READ,
+                            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                            buffer: params,
+                            offset: 0,
+                            size: vk::WHOLE_SIZE,
+                            ..Default::default()
+                        }],
+                        &barriers,
+                    );
+                    if let Some(buffer) = convergence {
+                        let raw = ConvergenceRaw {
+                            scattering_groups,
+                            order_reached: 1,
+                            irradiance_groups,
+                            groups_done: 0,
+                        };
+                        device.cmd_update_buffer(
+                            cmd,
+                            buffer,
+                            0,
+                            &mem::transmute::<ConvergenceRaw, [u8; 32]>(raw),
+                        );
+                        device.cmd_pipeline_barrier(
+                            cmd,
+                            vk::PipelineStageFlags::TRANSFER,
+                            vk::PipelineStageFlags::DRAW_INDIRECT
+                                | vk::PipelineStageFlags::COMPUTE_SHADER,
+                            Default::default(),
+                            &[],
+                            &[vk::BufferMemoryBarrier {
+                                src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+                                dst_access_mask: vk::AccessFlags::INDIRECT_COMMAND_READ
+                                    | vk::AccessFlags::SHADER_READ
+                                    | vk::AccessFlags::SHADER_WRITE,
+                                src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                                dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                                buffer,
+                                offset: 0,
+                                size: vk::WHOLE_SIZE,
+                                ..Default::default()
+                            }],
+                            &[],
+                        );
+                    }
 
-                // Indirect irradiance
-                device.cmd_bind_pipeline(
-                    cmd,
-                    vk::PipelineBindPoint::COMPUTE,
-                    builder.indirect_irradiance.pipeline,
-                );
-                device.cmd_bind_descriptor_sets(
-                    cmd,
-                    vk::PipelineBindPoint::COMPUTE,
-                    builder.indirect_irradiance.layout,
-                    0,
-                    &[params_ds, indirect_irradiance_ds],
-                    &[],
-                );
-                device.cmd_push_constants(
-                    cmd,
-                    builder.indirect_irradiance.layout,
-                    vk::ShaderStageFlags::COMPUTE,
-                    0,
-                    &(order - 1).to_ne_bytes(),
-                );
-                device.cmd_dispatch(
-                    cmd,
-                    irradiance_extent.width / WORKGROUP_SIZE_2D,
-                    irradiance_extent.height / WORKGROUP_SIZE_2D,
-                    1,
-                );
+                    if !self.reuse_transmittance {
+                        device.cmd_bind_pipeline(
+                            cmd,
+                            vk::PipelineBindPoint::COMPUTE,
+                            builder.transmittance.pipeline,
+                        );
+                        device.cmd_bind_descriptor_sets(
+                            cmd,
+                            vk::PipelineBindPoint::COMPUTE,
+                            builder.transmittance.layout,
+                            0,
+                            &[params_ds, transmittance_ds],
+                            &[],
+                        );
+                        device.cmd_dispatch(
+                            cmd,
+                            transmittance_extent.width.div_ceil(size_2d),
+                            transmittance_extent.height.div_ceil(size_2d),
+                            1,
+                        );
 
-                device.cmd_pipeline_barrier(
-                    cmd,
-                    vk::PipelineStageFlags::COMPUTE_SHADER,
-                    vk::PipelineStageFlags::COMPUTE_SHADER,
-                    Default::default(),
-                    &[],
-                    &[],
-                    &[
-                        vk::ImageMemoryBarrier {
-                            image: scattering_density.handle,
-                            ..write_read_barrier
+                        device.cmd_pipeline_barrier(
+                            cmd,
+                            vk::PipelineStageFlags::COMPUTE_SHADER,
+                            vk::PipelineStageFlags::COMPUTE_SHADER,
+                            Default::default(),
+                            &[],
+                            &[],
+                            &[vk::ImageMemoryBarrier {
+                                image: transmittance.handle,
+                                ..write_read_barrier
+                            }],
+                        );
+                    }
+                }
+                Step::DirectIrradiance => {
+                    device.cmd_bind_pipeline(
+                        cmd,
+                        vk::PipelineBindPoint::COMPUTE,
+                        builder.direct_irradiance.pipeline,
+                    );
+                    device.cmd_bind_descriptor_sets(
+                        cmd,
+                        vk::PipelineBindPoint::COMPUTE,
+                        builder.direct_irradiance.layout,
+                        0,
+                        &[params_ds, direct_irradiance_ds],
+                        &[],
+                    );
+                    device.cmd_dispatch(
+                        cmd,
+                        irradiance_extent.width.div_ceil(size_2d),
+                        irradiance_extent.height.div_ceil(size_2d),
+                        1,
+                    );
+                }
+                Step::SingleScattering => {
+                    if self.reuse_single_scattering {
+                        // Single scattering initializes the scattering texture
+                        device.cmd_pipeline_barrier(
+                            cmd,
+                            vk::PipelineStageFlags::COMPUTE_SHADER,
+                            vk::PipelineStageFlags::TRANSFER,
+                            Default::default(),
+                            &[],
+                            &[],
+                            &[
+                                vk::ImageMemoryBarrier {
+                                    image: delta_rayleigh.handle,
+                                    dst_access_mask: vk::AccessFlags::TRANSFER_READ,
+                                    old_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                                    new_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                                    ..init_barrier
+                                },
+                                vk::ImageMemoryBarrier {
+                                    image: scattering.handle,
+                                    dst_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+                                    old_layout: vk::ImageLayout::GENERAL,
+                                    new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                                    ..init_barrier
+                                },
+                            ],
+                        );
+                        let layers = vk::ImageSubresourceLayers {
+                            aspect_mask: vk::ImageAspectFlags::COLOR,
+                            mip_level: 0,
+                            base_array_layer: 0,
+                            layer_count: 1,
+                        };
+                        device.cmd_copy_image(
+                            cmd,
+                            delta_rayleigh.handle,
+                            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                            scattering.handle,
+                            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                            &[vk::ImageCopy {
+                                src_subresource: layers,
+                                src_offset: vk::Offset3D::default(),
+                                dst_subresource: layers,
+                                dst_offset: vk::Offset3D::default(),
+                                extent: scattering_extent,
+                            }],
+                        );
+                    } else {
+                        device.cmd_bind_pipeline(
+                            cmd,
+                            vk::PipelineBindPoint::COMPUTE,
+                            builder.single_scattering.pipeline,
+                        );
+                        device.cmd_bind_descriptor_sets(
+                            cmd,
+                            vk::PipelineBindPoint::COMPUTE,
+                            builder.single_scattering.layout,
+                            0,
+                            &[params_ds, single_scattering_ds],
+                            &[],
+                        );
+                        device.cmd_dispatch(
+                            cmd,
+                            scattering_extent.width.div_ceil(size_3d),
+                            scattering_extent.height.div_ceil(size_3d),
+                            scattering_extent.depth.div_ceil(size_3d),
+                        );
+                    }
+
+                    device.cmd_clear_color_image(
+                        cmd,
+                        irradiance.handle,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        &vk::ClearColorValue {
+                            float32: [0.0, 0.0, 0.0, 0.0],
+                        },
+                        &[vk::ImageSubresourceRange {
+                            aspect_mask: vk::ImageAspectFlags::COLOR,
+                            base_mip_level: 0,
+                            level_count: 1,
+                            base_array_layer: 0,
+                            layer_count: 1,
+                        }],
+                    );
+                    device.cmd_clear_color_image(
+                        cmd,
+                        directional_irradiance.handle,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        &vk::ClearColorValue {
+                            float32: [0.0, 0.0, 0.0, 0.0],
                         },
+                        &[directional_irradiance_range],
+                    );
+
+                    let mut barriers = vec![
                         vk::ImageMemoryBarrier {
-                            image: scattering.handle,
+                            image: irradiance.handle,
+                            src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+                            old_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
                             ..write_barrier
                         },
                         vk::ImageMemoryBarrier {
-                            image: delta_multiple_scattering.handle,
-                            src_access_mask: vk::AccessFlags::SHADER_READ,
-                            ..init_barrier
+                            image: directional_irradiance.handle,
+                            src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+                            old_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                            subresource_range: directional_irradiance_range,
+                            ..write_barrier
                         },
-                    ],
-                );
+                    ];
+                    if self.reuse_single_scattering {
+                        barriers.push(vk::ImageMemoryBarrier {
+                            image: scattering.handle,
+                            src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+                            old_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                            ..write_barrier
+                        });
+                        barriers.push(vk::ImageMemoryBarrier {
+                            image: delta_rayleigh.handle,
+                            src_access_mask: vk::AccessFlags::default(),
+                            old_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                            ..write_read_barrier
+                        });
+                    }
+                    device.cmd_pipeline_barrier(
+                        cmd,
+                        vk::PipelineStageFlags::TRANSFER,
+                        vk::PipelineStageFlags::COMPUTE_SHADER,
+                        Default::default(),
+                        &[],
+                        &[],
+                        &barriers,
+                    );
 
-                // Multiscattering
-                device.cmd_bind_pipeline(
-                    cmd,
-                    vk::PipelineBindPoint::COMPUTE,
-                    builder.multiple_scattering.pipeline,
-                );
-                device.cmd_bind_descriptor_sets(
-                    cmd,
-                    vk::PipelineBindPoint::COMPUTE,
-                    builder.multiple_scattering.layout,
-                    0,
-                    &[params_ds, multiple_scattering_ds],
-                    &[],
-                );
-                device.cmd_dispatch(
+                    if !self.reuse_single_scattering {
+                        device.cmd_pipeline_barrier(
+                            cmd,
+                            vk::PipelineStageFlags::COMPUTE_SHADER,
+                            vk::PipelineStageFlags::COMPUTE_SHADER,
+                            Default::default(),
+                            &[],
+                            &[],
+                            &[
+                                vk::ImageMemoryBarrier {
+                                    image: delta_rayleigh.handle,
+                                    ..write_read_barrier
+                                },
+                                vk::ImageMemoryBarrier {
+                                    image: delta_mie.handle,
+                                    ..write_read_barrier
+                                },
+                            ],
+                        );
+                    }
+
+                    if let Some(export) = pending.exports.first() {
+                        let export_barrier = vk::ImageMemoryBarrier {
+                            dst_access_mask: vk::AccessFlags::TRANSFER_READ,
+                            old_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                            new_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                            ..write_barrier
+                        };
+                        device.cmd_pipeline_barrier(
+                            cmd,
+                            vk::PipelineStageFlags::COMPUTE_SHADER,
+                            vk::PipelineStageFlags::TRANSFER,
+                            Default::default(),
+                            &[],
+                            &[],
+                            &[
+                                vk::ImageMemoryBarrier {
+                                    image: delta_irradiance.handle,
+                                    dst_access_mask: vk::AccessFlags::TRANSFER_READ,
+                                    ..write_barrier
+                                },
+                                vk::ImageMemoryBarrier {
+                                    image: delta_rayleigh.handle,
+                                    ..export_barrier
+                                },
+                                vk::ImageMemoryBarrier {
+                                    image: delta_mie.handle,
+                                    ..export_barrier
+                                },
+                            ],
+                        );
+                        export_order(
+                            export.buffer,
+                            [
+                                (delta_irradiance.handle, vk::ImageLayout::GENERAL),
+                                (delta_rayleigh.handle, vk::ImageLayout::TRANSFER_SRC_OPTIMAL),
+                                (delta_mie.handle, vk::ImageLayout::TRANSFER_SRC_OPTIMAL),
+                            ],
+                        );
+                        device.cmd_pipeline_barrier(
+                            cmd,
+                            vk::PipelineStageFlags::TRANSFER,
+                            vk::PipelineStageFlags::COMPUTE_SHADER | vk::PipelineStageFlags::HOST,
+                            Default::default(),
+                            &[vk::MemoryBarrier {
+                                src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+                                dst_access_mask: vk::AccessFlags::HOST_READ,
+                                ..Default::default()
+                            }],
+                            &[],
+                            &[
+                                vk::ImageMemoryBarrier {
+                                    image: delta_rayleigh.handle,
+                                    src_access_mask: vk::AccessFlags::default(),
+                                    old_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                                    ..write_read_barrier
+                                },
+                                vk::ImageMemoryBarrier {
+                                    image: delta_mie.handle,
+                                    src_access_mask: vk::AccessFlags::default(),
+                                    old_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                                    ..write_read_barrier
+                                },
+                            ],
+                        );
+                    }
+                }
+                Step::ScatteringDensity(order) => {
+                    device.cmd_pipeline_barrier(
+                        cmd,
+                        vk::PipelineStageFlags::COMPUTE_SHADER,
+                        vk::PipelineStageFlags::COMPUTE_SHADER,
+                        Default::default(),
+                        &[],
+                        &[],
+                        &[
+                            vk::ImageMemoryBarrier {
+                                image: scattering_density.handle,
+                                src_access_mask: vk::AccessFlags::SHADER_READ,
+                                ..init_barrier
+                            },
+                            vk::ImageMemoryBarrier {
+                                image: delta_irradiance.handle,
+                                ..write_read_barrier
+                            },
+                            // Left in GENERAL, as a layout transition could disturb delta_rayleigh,
+                            // which shares its memory and is still read in the second order
+                            vk::ImageMemoryBarrier {
+                                image: delta_multiple_scattering.handle,
+                                new_layout: vk::ImageLayout::GENERAL,
+                                ..write_read_barrier
+                            },
+                        ],
+                    );
+
+                    device.cmd_bind_pipeline(
+                        cmd,
+                        vk::PipelineBindPoint::COMPUTE,
+                        builder.scattering_density.pipeline,
+                    );
+                    device.cmd_bind_descriptor_sets(
+                        cmd,
+                        vk::PipelineBindPoint::COMPUTE,
+                        builder.scattering_density.layout,
+                        0,
+                        &[params_ds, scattering_density_ds],
+                        &[],
+                    );
+                    device.cmd_push_constants(
+                        cmd,
+                        builder.scattering_density.layout,
+                        vk::ShaderStageFlags::COMPUTE,
+                        0,
+                        &order.to_ne_bytes(),
+                    );
+                    dispatch_order(scattering_groups, 0);
+
+                    device.cmd_pipeline_barrier(
+                        cmd,
+                        vk::PipelineStageFlags::COMPUTE_SHADER,
+                        vk::PipelineStageFlags::COMPUTE_SHADER,
+                        Default::default(),
+                        &[],
+                        &[],
+                        &[
+                            // Scattering density reads this
+                            vk::ImageMemoryBarrier {
+                                image: delta_irradiance.handle,
+                                ..read_write_barrier
+                            },
+                            // Previous irradiance pass output must be written
+                            vk::ImageMemoryBarrier {
+                                image: irradiance.handle,
+                                ..write_barrier
+                            },
+                            vk::ImageMemoryBarrier {
+                                image: directional_irradiance.handle,
+                                subresource_range: directional_irradiance_range,
+                                ..write_barrier
+                            },
+                        ],
+                    );
+                }
+                Step::IndirectIrradiance(order) => {
+                    device.cmd_bind_pipeline(
+                        cmd,
+                        vk::PipelineBindPoint::COMPUTE,
+                        builder.indirect_irradiance.pipeline,
+                    );
+                    device.cmd_bind_descriptor_sets(
+                        cmd,
+                        vk::PipelineBindPoint::COMPUTE,
+                        builder.indirect_irradiance.layout,
+                        0,
+                        &[params_ds, indirect_irradiance_ds],
+                        &[],
+                    );
+                    device.cmd_push_constants(
+                        cmd,
+                        builder.indirect_irradiance.layout,
+                        vk::ShaderStageFlags::COMPUTE,
+                        0,
+                        &(order - 1).to_ne_bytes(),
+                    );
+                    dispatch_order(irradiance_groups, 16);
+
+                    device.cmd_pipeline_barrier(
+                        cmd,
+                        vk::PipelineStageFlags::COMPUTE_SHADER,
+                        vk::PipelineStageFlags::COMPUTE_SHADER,
+                        Default::default(),
+                        &[],
+                        &[],
+                        &[
+                            vk::ImageMemoryBarrier {
+                                image: scattering_density.handle,
+                                ..write_read_barrier
+                            },
+                            vk::ImageMemoryBarrier {
+                                image: scattering.handle,
+                                ..write_barrier
+                            },
+                            vk::ImageMemoryBarrier {
+                                image: delta_multiple_scattering.handle,
+                                src_access_mask: vk::AccessFlags::SHADER_READ,
+                                ..init_barrier
+                            },
+                        ],
+                    );
+                }
+                Step::MultipleScattering(order) => {
+                    device.cmd_bind_pipeline(
+                        cmd,
+                        vk::PipelineBindPoint::COMPUTE,
+                        builder.multiple_scattering.pipeline,
+                    );
+                    device.cmd_bind_descriptor_sets(
+                        cmd,
+                        vk::PipelineBindPoint::COMPUTE,
+                        builder.multiple_scattering.layout,
+                        0,
+                        &[params_ds, multiple_scattering_ds],
+                        &[],
+                    );
+                    dispatch_order(scattering_groups, 0);
+
+                    if let (Some(buffer), Some(threshold)) =
+                        (convergence, self.convergence_threshold)
+                    {
+                        device.cmd_pipeline_barrier(
+                            cmd,
+                            vk::PipelineStageFlags::COMPUTE_SHADER,
+                            vk::PipelineStageFlags::COMPUTE_SHADER,
+                            Default::default(),
+                            &[vk::MemoryBarrier {
+                                src_access_mask: vk::AccessFlags::SHADER_WRITE,
+                                dst_access_mask: vk::AccessFlags::SHADER_READ,
+                                ..Default::default()
+                            }],
+                            &[],
+                            &[],
+                        );
+                        device.cmd_bind_pipeline(
+                            cmd,
+                            vk::PipelineBindPoint::COMPUTE,
+                            builder.convergence.pipeline,
+                        );
+                        device.cmd_bind_descriptor_sets(
+                            cmd,
+                            vk::PipelineBindPoint::COMPUTE,
+                            builder.convergence.layout,
+                            0,
+                            &[params_ds, convergence_ds],
+                            &[],
+                        );
+                        device.cmd_push_constants(
+                            cmd,
+                            builder.convergence.layout,
+                            vk::ShaderStageFlags::COMPUTE,
+                            0,
+                            &mem::transmute::<[u32; 2], [u8; 8]>([order, threshold.to_bits()]),
+                        );
+                        device.cmd_dispatch(cmd, convergence_groups(scattering_extent), 1, 1);
+                        device.cmd_pipeline_barrier(
+                            cmd,
+                            vk::PipelineStageFlags::COMPUTE_SHADER,
+                            vk::PipelineStageFlags::DRAW_INDIRECT
+                                | vk::PipelineStageFlags::COMPUTE_SHADER,
+                            Default::default(),
+                            &[],
+                            &[vk::BufferMemoryBarrier {
+                                src_access_mask: vk::AccessFlags::SHADER_WRITE,
+                                dst_access_mask: vk::AccessFlags::INDIRECT_COMMAND_READ
+                                    | vk::AccessFlags::SHADER_READ
+                                    | vk::AccessFlags::SHADER_WRITE,
+                                src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                                dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                                buffer,
+                                offset: 0,
+                                size: vk::WHOLE_SIZE,
+                                ..Default::default()
+                            }],
+                            &[],
+                        );
+                    }
+
+                    if let Some(export) = pending.exports.get(order as usize - 1) {
+                        device.cmd_pipeline_barrier(
+                            cmd,
+                            vk::PipelineStageFlags::COMPUTE_SHADER,
+                            vk::PipelineStageFlags::TRANSFER,
+                            Default::default(),
+                            &[],
+                            &[],
+                            &[
+                                vk::ImageMemoryBarrier {
+                                    image: delta_irradiance.handle,
+                                    dst_access_mask: vk::AccessFlags::TRANSFER_READ,
+                                    ..write_barrier
+                                },
+                                vk::ImageMemoryBarrier {
+                                    image: delta_multiple_scattering.handle,
+                                    dst_access_mask: vk::AccessFlags::TRANSFER_READ,
+                                    ..write_barrier
+                                },
+                                // Discarded by the next order, so needn't be transitioned back
+                                vk::ImageMemoryBarrier {
+                                    image: scattering_density.handle,
+                                    src_access_mask: vk::AccessFlags::default(),
+                                    dst_access_mask: vk::AccessFlags::TRANSFER_READ,
+                                    old_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                                    new_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                                    ..write_barrier
+                                },
+                            ],
+                        );
+                        export_order(
+                            export.buffer,
+                            [
+                                (delta_irradiance.handle, vk::ImageLayout::GENERAL),
+                                (delta_multiple_scattering.handle, vk::ImageLayout::GENERAL),
+                                (
+                                    scattering_density.handle,
+                                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                                ),
+                            ],
+                        );
+                        device.cmd_pipeline_barrier(
+                            cmd,
+                            vk::PipelineStageFlags::TRANSFER,
+                            vk::PipelineStageFlags::COMPUTE_SHADER | vk::PipelineStageFlags::HOST,
+                            Default::default(),
+                            &[vk::MemoryBarrier {
+                                src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+                                dst_access_mask: vk::AccessFlags::HOST_READ,
+                                ..Default::default()
+                            }],
+                            &[],
+                            &[],
+                        );
+                    }
+                }
+                Step::Finalize => {
+                    if let Some(buffer) = validation {
+                        let empty = InvalidRaw {
+                            count: 0,
+                            first: u32::MAX,
+                            _padding: [0; 2],
+                            coords: [0.0; 4],
+                        };
+                        let raw = LutReportRaw {
+                            invalid: [[empty; 2]; 3],
+                        };
+                        device.cmd_update_buffer(
+                            cmd,
+                            buffer,
+                            0,
+                            &mem::transmute::<LutReportRaw, [u8; 192]>(raw),
+                        );
+                        device.cmd_pipeline_barrier(
+                            cmd,
+                            vk::PipelineStageFlags::COMPUTE_SHADER
+                                | vk::PipelineStageFlags::TRANSFER,
+                            vk::PipelineStageFlags::COMPUTE_SHADER,
+                            Default::default(),
+                            &[vk::MemoryBarrier {
+                                src_access_mask: vk::AccessFlags::SHADER_WRITE
+                                    | vk::AccessFlags::TRANSFER_WRITE,
+                                dst_access_mask: vk::AccessFlags::SHADER_READ
+                                    | vk::AccessFlags::SHADER_WRITE,
+                                ..Default::default()
+                            }],
+                            &[],
+                            &[],
+                        );
+                        device.cmd_bind_pipeline(
+                            cmd,
+                            vk::PipelineBindPoint::COMPUTE,
+                            builder.validation.pipeline,
+                        );
+                        device.cmd_bind_descriptor_sets(
+                            cmd,
+                            vk::PipelineBindPoint::COMPUTE,
+                            builder.validation.layout,
+                            0,
+                            &[params_ds, validation_ds],
+                            &[],
+                        );
+                        for (table, &[x, y, z]) in [
+                            [transmittance_extent.width, transmittance_extent.height, 1],
+                            [irradiance_extent.width, irradiance_extent.height, 1],
+                            [
+                                scattering_extent.width,
+                                scattering_extent.height,
+                                scattering_extent.depth,
+                            ],
+                        ]
+                        .iter()
+                        .enumerate()
+                        {
+                            device.cmd_push_constants(
+                                cmd,
+                                builder.validation.layout,
+                                vk::ShaderStageFlags::COMPUTE,
+                                0,
+                                &(table as u32).to_ne_bytes(),
+                            );
+                            device.cmd_dispatch(cmd, x.div_ceil(8), y.div_ceil(8), z);
+                        }
+                        device.cmd_pipeline_barrier(
+                            cmd,
+                            vk::PipelineStageFlags::COMPUTE_SHADER,
+                            vk::PipelineStageFlags::COMPUTE_SHADER,
+                            Default::default(),
+                            &[],
+                            &[vk::BufferMemoryBarrier {
+                                src_access_mask: vk::AccessFlags::SHADER_WRITE,
+                                dst_access_mask: vk::AccessFlags::SHADER_READ
+                                    | vk::AccessFlags::SHADER_WRITE,
+                                src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                                dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                                buffer,
+                                offset: 0,
+                                size: vk::WHOLE_SIZE,
+                                ..Default::default()
+                            }],
+                            &[],
+                        );
+                        // Find the parameters of the first invalid texels
+                        device.cmd_push_constants(
+                            cmd,
+                            builder.validation.layout,
+                            vk::ShaderStageFlags::COMPUTE,
+                            0,
+                            &3u32.to_ne_bytes(),
+                        );
+                        device.cmd_dispatch(cmd, 1, 1, 1);
+                        // Make the report visible to `PendingAtmosphere::lut_report`
+                        device.cmd_pipeline_barrier(
+                            cmd,
+                            vk::PipelineStageFlags::COMPUTE_SHADER,
+                            vk::PipelineStageFlags::HOST,
+                            Default::default(),
+                            &[],
+                            &[vk::BufferMemoryBarrier {
+                                src_access_mask: vk::AccessFlags::SHADER_WRITE,
+                                dst_access_mask: vk::AccessFlags::HOST_READ,
+                                src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                                dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                                buffer,
+                                offset: 0,
+                                size: vk::WHOLE_SIZE,
+                                ..Default::default()
+                            }],
+                            &[],
+                        );
+                    }
+                    if let Some(buffer) = convergence {
+                        // Make the order reached visible to `PendingAtmosphere::assert_ready`
+                        device.cmd_pipeline_barrier(
+                            cmd,
+                            vk::PipelineStageFlags::COMPUTE_SHADER,
+                            vk::PipelineStageFlags::HOST,
+                            Default::default(),
+                            &[],
+                            &[vk::BufferMemoryBarrier {
+                                src_access_mask: vk::AccessFlags::SHADER_WRITE,
+                                dst_access_mask: vk::AccessFlags::HOST_READ,
+                                src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                                dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                                buffer,
+                                offset: 0,
+                                size: vk::WHOLE_SIZE,
+                                ..Default::default()
+                            }],
+                            &[],
+                        );
+                    }
+                    // Finalize layouts and transfer to graphics queue
+                    let src_queue_family_index = builder
+                        .compute_queue_family
+                        .unwrap_or(builder.gfx_queue_family);
+                    device.cmd_pipeline_barrier(
+                        cmd,
+                        vk::PipelineStageFlags::COMPUTE_SHADER,
+                        self.dst_stage_mask,
+                        Default::default(),
+                        &[],
+                        &[vk::BufferMemoryBarrier {
+                            src_access_mask: vk::AccessFlags::UNIFORM_READ,
+                            src_queue_family_index,
+                            dst_queue_family_index: builder.gfx_queue_family,
+                            buffer: params,
+                            offset: 0,
+                            size: vk::WHOLE_SIZE,
+                            ..Default::default()
+                        }],
+                        &[
+                            vk::ImageMemoryBarrier {
+                                image: scattering.handle,
+                                dst_access_mask: self.dst_access_mask,
+                                new_layout: self.layout,
+                                src_queue_family_index,
+                                dst_queue_family_index: builder.gfx_queue_family,
+                                ..write_read_barrier
+                            },
+                            vk::ImageMemoryBarrier {
+                                image: irradiance.handle,
+                                dst_access_mask: self.dst_access_mask,
+                                new_layout: self.layout,
+                                src_queue_family_index,
+                                dst_queue_family_index: builder.gfx_queue_family,
+                                ..write_read_barrier
+                            },
+                            vk::ImageMemoryBarrier {
+                                image: directional_irradiance.handle,
+                                dst_access_mask: self.dst_access_mask,
+                                new_layout: self.layout,
+                                src_queue_family_index,
+                                dst_queue_family_index: builder.gfx_queue_family,
+                                subresource_range: directional_irradiance_range,
+                                ..write_read_barrier
+                            },
+                            // Shared between queue families if they differ
+                            vk::ImageMemoryBarrier {
+                                image: transmittance.handle,
+                                src_access_mask: vk::AccessFlags::default(),
+                                dst_access_mask: self.dst_access_mask,
+                                old_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                                new_layout: self.layout,
+                                ..write_read_barrier
+                            },
+                        ],
+                    );
+                }
+                Step::Done => return true,
+            }
+            if let Some(debug) = debug {
+                debug.end_label(cmd);
+            }
+            if let Some(ref x) = pending.timestamps {
+                device.cmd_write_timestamp(
                     cmd,
-                    scattering_extent.width / WORKGROUP_SIZE_3D,
-                    scattering_extent.height / WORKGROUP_SIZE_3D,
-                    scattering_extent.depth / WORKGROUP_SIZE_3D,
+                    vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                    x.pool,
+                    self.recorded + 1,
                 );
             }
+        }
 
-            // Finalize layouts and transfer to graphics queue
-            let src_queue_family_index = builder
-                .compute_queue_family
-                .unwrap_or(builder.gfx_queue_family);
-            device.cmd_pipeline_barrier(
-                cmd,
-                vk::PipelineStageFlags::COMPUTE_SHADER,
-                atmosphere_params.dst_stage_mask,
-                Default::default(),
-                &[],
-                &[vk::BufferMemoryBarrier {
-                    src_access_mask: vk::AccessFlags::UNIFORM_READ,
-                    src_queue_family_index,
-                    dst_queue_family_index: builder.gfx_queue_family,
-                    buffer: params,
-                    offset: 0,
-                    size: vk::WHOLE_SIZE,
-                    ..Default::default()
-                }],
-                &[
-                    vk::ImageMemoryBarrier {
-                        image: scattering.handle,
-                        dst_access_mask: atmosphere_params.dst_access_mask,
-                        new_layout: atmosphere_params.layout,
-                        src_queue_family_index,
-                        dst_queue_family_index: builder.gfx_queue_family,
-                        ..write_read_barrier
-                    },
-                    vk::ImageMemoryBarrier {
-                        image: irradiance.handle,
-                        dst_access_mask: atmosphere_params.dst_access_mask,
-                        new_layout: atmosphere_params.layout,
-                        src_queue_family_index,
-                        dst_queue_family_index: builder.gfx_queue_family,
-                        ..write_read_barrier
-                    },
-                    vk::ImageMemoryBarrier {
-                        image: transmittance.handle,
-                        src_access_mask: vk::AccessFlags::default(),
-                        dst_access_mask: atmosphere_params.dst_access_mask,
-                        old_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
-                        new_layout: atmosphere_params.layout,
-                        src_queue_family_index,
-                        dst_queue_family_index: builder.gfx_queue_family,
-                        ..write_read_barrier
-                    },
-                ],
-            );
-
-            PendingAtmosphere {
-                device: builder.device.clone(),
-                descriptor_pool,
-                inner: Some(Self {
-                    builder,
-                    descriptor_pool: persistent_pool,
-                    ds: render_ds,
-                    transmittance,
-                    transmittance_extent,
-                    scattering,
-                    scattering_extent,
-                    irradiance,
-                    irradiance_extent,
-                    params,
-                    params_mem,
-                }),
-                delta_irradiance,
-                delta_mie,
-                delta_rayleigh,
-                scattering_density,
-                delta_multiple_scattering,
+        self.step = match self.step {
+            Step::Transmittance => Step::DirectIrradiance,
+            Step::DirectIrradiance => Step::SingleScattering,
+            Step::SingleScattering if self.order >= 2 => Step::ScatteringDensity(2),
+            Step::SingleScattering => Step::Finalize,
+            Step::ScatteringDensity(order) => Step::IndirectIrradiance(order),
+            Step::IndirectIrradiance(order) => Step::MultipleScattering(order),
+            Step::MultipleScattering(order) if order < self.order => {
+                Step::ScatteringDensity(order + 1)
             }
-        }
+            Step::MultipleScattering(_) => Step::Finalize,
+            Step::Finalize | Step::Done => Step::Done,
+        };
+        self.recorded += 1;
+        self.step == Step::Done
     }
 
-    pub fn transmittance(&self) -> vk::Image {
-        self.transmittance.handle
-    }
-    pub fn transmittance_view(&self) -> vk::ImageView {
-        self.transmittance.view
-    }
-    pub fn transmittance_extent(&self) -> vk::Extent2D {
-        self.transmittance_extent
-    }
-    pub fn scattering(&self) -> vk::Image {
-        self.scattering.handle
-    }
-    pub fn scattering_view(&self) -> vk::ImageView {
-        self.scattering.view
+    /// Fraction of steps recorded so far, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        self.recorded as f32 / self.step_count() as f32
     }
-    pub fn scattering_extent(&self) -> vk::Extent3D {
-        self.scattering_extent
-    }
-    pub fn irradiance(&self) -> vk::Image {
-        self.irradiance.handle
-    }
-    pub fn irradiance_view(&self) -> vk::ImageView {
-        self.irradiance.view
+
+    /// Total number of steps to record
+    pub fn step_count(&self) -> u32 {
+        step_count(self.order)
     }
-    pub fn irradiance_extent(&self) -> vk::Extent2D {
-        self.irradiance_extent
+
+    /// Access the atmosphere being built, once every step has been recorded
+    ///
+    /// Panics if `record` hasn't yet returned `true`.
+    pub fn finish(self) -> PendingAtmosphere {
+        assert!(
+            self.step == Step::Done,
+            "precompute steps remain unrecorded"
+        );
+        self.pending
     }
+}
 
-    pub(crate) fn descriptor_set(&self) -> vk::DescriptorSet {
-        self.ds
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Step {
+    Transmittance,
+    DirectIrradiance,
+    SingleScattering,
+    ScatteringDensity(u32),
+    IndirectIrradiance(u32),
+    MultipleScattering(u32),
+    Finalize,
+    Done,
+}
+
+impl Step {
+    /// Debug label for the commands recorded by this step
+    fn label(self) -> String {
+        match self {
+            Step::Transmittance => "fuzzyblue transmittance".into(),
+            Step::DirectIrradiance => "fuzzyblue direct irradiance".into(),
+            Step::SingleScattering => "fuzzyblue single scattering".into(),
+            Step::ScatteringDensity(order) => {
+                format!("fuzzyblue scattering density (order {})", order)
+            }
+            Step::IndirectIrradiance(order) => {
+                format!("fuzzyblue indirect irradiance (order {})", order)
+            }
+            Step::MultipleScattering(order) => {
+                format!("fuzzyblue multiple scattering (order {})", order)
+            }
+            Step::Finalize => "fuzzyblue finalize".into(),
+            Step::Done => unreachable!(),
+        }
     }
 }
 
@@ -2110,28 +4120,80 @@ impl Atmosphere {
 /// Must not be dropped before the `vk::CommandBuffer` passed to `Builder::build` has completed execution
 pub struct PendingAtmosphere {
     device: Arc<Device>,
+    allocator: Arc<dyn Allocator>,
     descriptor_pool: vk::DescriptorPool,
     inner: Option<Atmosphere>,
+    /// Backs all of the following images
+    temporary_memory: Allocation,
     delta_irradiance: Image,
-    delta_rayleigh: Image,
-    delta_mie: Image,
+    /// Absent if retained by the `Atmosphere`
+    single_scattering: Option<SingleScattering>,
     scattering_density: Image,
     delta_multiple_scattering: Image,
+    /// Present if `Parameters::convergence_threshold` was set
+    convergence: Option<Convergence>,
+    /// Present if `Parameters::validate_luts` was set
+    validation: Option<Validation>,
+    /// One per scattering order if `Parameters::export_orders` was set
+    exports: Vec<Export>,
+    /// Present if `Parameters::record_timings` was set
+    timestamps: Option<Timestamps>,
+}
+
+/// Host-visible `ConvergenceRaw` shared with the convergence pass
+struct Convergence {
+    buffer: vk::Buffer,
+    memory: Allocation,
+}
+
+/// Host-visible `LutReportRaw` written by the validation pass
+struct Validation {
+    buffer: vk::Buffer,
+    memory: Allocation,
+}
+
+/// Host-visible copies of one scattering order's delta irradiance and two scattering-sized
+/// tables, laid out as given by `export_offsets`
+struct Export {
+    buffer: vk::Buffer,
+    memory: Allocation,
+}
+
+struct Timestamps {
+    pool: vk::QueryPool,
+    count: u32,
 }
 
 impl Drop for PendingAtmosphere {
     fn drop(&mut self) {
         unsafe {
-            for &image in &[
+            let mut images = vec![
                 &self.delta_irradiance,
-                &self.delta_rayleigh,
-                &self.delta_mie,
                 &self.scattering_density,
                 &self.delta_multiple_scattering,
-            ] {
+            ];
+            if let Some(ref x) = self.single_scattering {
+                images.extend(&[&x.rayleigh, &x.mie]);
+            }
+            for image in images {
                 self.device.destroy_image_view(image.view, None);
                 self.device.destroy_image(image.handle, None);
-                self.device.free_memory(image.memory, None);
+            }
+            self.allocator.free(self.temporary_memory);
+            if let Some(ref x) = self.convergence {
+                self.device.destroy_buffer(x.buffer, None);
+                self.allocator.free(x.memory);
+            }
+            if let Some(ref x) = self.validation {
+                self.device.destroy_buffer(x.buffer, None);
+                self.allocator.free(x.memory);
+            }
+            for x in &self.exports {
+                self.device.destroy_buffer(x.buffer, None);
+                self.allocator.free(x.memory);
+            }
+            if let Some(ref x) = self.timestamps {
+                self.device.destroy_query_pool(x.pool, None);
             }
             self.device
                 .destroy_descriptor_pool(self.descriptor_pool, None);
@@ -2182,24 +4244,170 @@ impl PendingAtmosphere {
                 size: vk::WHOLE_SIZE,
                 ..Default::default()
             }],
+            // Transmittance is shared between queue families, so needs no transfer
             &[
                 vk::ImageMemoryBarrier {
-                    image: inner.transmittance.handle,
-                    old_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    image: inner.scattering.handle,
                     ..barrier
                 },
                 vk::ImageMemoryBarrier {
-                    image: inner.scattering.handle,
+                    image: inner.irradiance.handle,
                     ..barrier
                 },
                 vk::ImageMemoryBarrier {
-                    image: inner.irradiance.handle,
+                    image: inner.directional_irradiance.handle,
+                    subresource_range: vk::ImageSubresourceRange {
+                        layer_count: DIRECTIONAL_IRRADIANCE_LAYERS,
+                        ..barrier.subresource_range
+                    },
                     ..barrier
                 },
             ],
         );
     }
 
+    /// GPU time spent on each precompute step, if `Parameters::record_timings` was set
+    ///
+    /// Call when the `vk::CommandBuffer`s precompute was recorded into have completed execution.
+    pub unsafe fn timings(&self) -> Option<PrecomputeTimings> {
+        let timestamps = self.timestamps.as_ref()?;
+        let builder = &*self.inner.as_ref().unwrap().builder;
+        let mut ticks = vec![0u64; timestamps.count as usize];
+        self.device
+            .get_query_pool_results(
+                timestamps.pool,
+                0,
+                timestamps.count,
+                &mut ticks,
+                vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WAIT,
+            )
+            .unwrap();
+        let steps = ticks
+            .windows(2)
+            .map(|x| {
+                let ticks = x[1].wrapping_sub(x[0]) & builder.timestamp_mask;
+                Duration::from_nanos((ticks as f64 * f64::from(builder.timestamp_period)) as u64)
+            })
+            .collect::<Vec<_>>();
+        let (&finalize, steps) = steps.split_last().unwrap();
+        Some(PrecomputeTimings {
+            transmittance: steps[0],
+            direct_irradiance: steps[1],
+            single_scattering: steps[2],
+            orders: steps[3..]
+                .chunks(3)
+                .map(|x| OrderTimings {
+                    scattering_density: x[0],
+                    indirect_irradiance: x[1],
+                    multiple_scattering: x[2],
+                })
+                .collect(),
+            finalize,
+        })
+    }
+
+    /// Non-finite and negative texels found in the look-up tables, if `Parameters::validate_luts`
+    /// was set
+    ///
+    /// Call when the `vk::CommandBuffer`s precompute was recorded into have completed execution.
+    pub unsafe fn lut_report(&self) -> Option<LutReport> {
+        let validation = self.validation.as_ref()?;
+        let atmosphere = self.inner.as_ref().unwrap();
+        let raw = *(self
+            .device
+            .map_memory(
+                validation.memory.memory,
+                validation.memory.offset,
+                mem::size_of::<LutReportRaw>() as vk::DeviceSize,
+                vk::MemoryMapFlags::empty(),
+            )
+            .unwrap() as *const LutReportRaw);
+        self.device.unmap_memory(validation.memory.memory);
+        let transmittance = atmosphere.transmittance_extent;
+        let irradiance = atmosphere.irradiance_extent;
+        let scattering = atmosphere.scattering_extent;
+        Some(LutReport {
+            transmittance: TableReport::new(
+                &raw.invalid[0],
+                [transmittance.width, transmittance.height, 1],
+                [true, false, false],
+            ),
+            irradiance: TableReport::new(
+                &raw.invalid[1],
+                [irradiance.width, irradiance.height, 1],
+                [false, true, false],
+            ),
+            scattering: TableReport::new(
+                &raw.invalid[2],
+                [scattering.width, scattering.height, scattering.depth],
+                [true, true, true],
+            ),
+        })
+    }
+
+    /// Each scattering order's intermediate results, if `Parameters::export_orders` was set
+    ///
+    /// Call when the `vk::CommandBuffer`s precompute was recorded into have completed execution.
+    pub unsafe fn order_exports(&self) -> Option<Vec<OrderExport>> {
+        if self.exports.is_empty() {
+            return None;
+        }
+        let atmosphere = self.inner.as_ref().unwrap();
+        let irradiance = atmosphere.irradiance_extent;
+        let scattering = atmosphere.scattering_extent;
+        let offsets = export_offsets(irradiance, scattering);
+        let irradiance_texels = (irradiance.width * irradiance.height) as usize;
+        let scattering_texels = (scattering.width * scattering.height * scattering.depth) as usize;
+        let exports = self
+            .exports
+            .iter()
+            .enumerate()
+            .map(|(index, export)| {
+                let data = self
+                    .device
+                    .map_memory(
+                        export.memory.memory,
+                        export.memory.offset,
+                        offsets[2],
+                        vk::MemoryMapFlags::empty(),
+                    )
+                    .unwrap() as *const u8;
+                let irradiance_data =
+                    std::slice::from_raw_parts(data as *const [f32; 4], irradiance_texels).to_vec();
+                let scattering_data = |offset: vk::DeviceSize| {
+                    std::slice::from_raw_parts(
+                        data.add(offset as usize) as *const [u16; 4],
+                        scattering_texels,
+                    )
+                    .iter()
+                    .map(|&[r, g, b, a]| {
+                        [f16_to_f32(r), f16_to_f32(g), f16_to_f32(b), f16_to_f32(a)]
+                    })
+                    .collect::<Vec<_>>()
+                };
+                let scattering_data_0 = scattering_data(offsets[0]);
+                let scattering_data_1 = scattering_data(offsets[1]);
+                self.device.unmap_memory(export.memory.memory);
+                if index == 0 {
+                    OrderExport {
+                        irradiance: irradiance_data,
+                        scattering: scattering_data_0,
+                        mie: Some(scattering_data_1),
+                        scattering_density: None,
+                    }
+                } else {
+                    OrderExport {
+                        irradiance: irradiance_data,
+                        scattering: scattering_data_0,
+                        mie: None,
+                        scattering_density: Some(scattering_data_1),
+                    }
+                }
+            })
+            .collect();
+        Some(exports)
+    }
+
     /// Access the `Atmosphere` while it may not yet be ready
     pub unsafe fn atmosphere(&self) -> &Atmosphere {
         self.inner.as_ref().unwrap()
@@ -2207,10 +4415,203 @@ impl PendingAtmosphere {
 
     /// Call when the `vk::CommandBuffer` passed to `Builder::build` has completed execution
     pub unsafe fn assert_ready(mut self) -> Atmosphere {
-        self.inner.take().unwrap()
+        let mut atmosphere = self.inner.take().unwrap();
+        if let Some(ref x) = self.convergence {
+            let raw = self
+                .device
+                .map_memory(
+                    x.memory.memory,
+                    x.memory.offset,
+                    mem::size_of::<ConvergenceRaw>() as vk::DeviceSize,
+                    vk::MemoryMapFlags::empty(),
+                )
+                .unwrap() as *const ConvergenceRaw;
+            atmosphere.scattering_order = (*raw).order_reached;
+            self.device.unmap_memory(x.memory.memory);
+        }
+        atmosphere
     }
 }
 
+/// GPU time spent on each step of building an `Atmosphere`
+///
+/// Each step includes the barriers recorded with it, and steps recorded separately with
+/// `IncrementalAtmosphere::record` include any idle time between their submissions.
+#[derive(Debug, Clone)]
+pub struct PrecomputeTimings {
+    /// Includes uploading parameters
+    pub transmittance: Duration,
+    pub direct_irradiance: Duration,
+    /// Includes clearing the irradiance look-up tables
+    pub single_scattering: Duration,
+    /// Scattering orders from the second onwards
+    pub orders: Vec<OrderTimings>,
+    /// Transition to the final layouts and queue family, and validation if
+    /// `Parameters::validate_luts` was set
+    pub finalize: Duration,
+}
+
+impl PrecomputeTimings {
+    pub fn total(&self) -> Duration {
+        self.transmittance
+            + self.direct_irradiance
+            + self.single_scattering
+            + self
+                .orders
+                .iter()
+                .map(OrderTimings::total)
+                .sum::<Duration>()
+            + self.finalize
+    }
+}
+
+/// Invalid texels found in each look-up table of an `Atmosphere`
+#[derive(Debug, Copy, Clone)]
+pub struct LutReport {
+    pub transmittance: TableReport,
+    pub irradiance: TableReport,
+    pub scattering: TableReport,
+}
+
+impl LutReport {
+    /// Whether every texel is finite and non-negative
+    pub fn is_valid(&self) -> bool {
+        [self.transmittance, self.irradiance, self.scattering]
+            .iter()
+            .all(|x| x.non_finite == 0 && x.negative == 0)
+    }
+}
+
+/// Invalid texels found in one look-up table
+///
+/// Texels with a non-finite component are counted as such even if another is negative.
+#[derive(Debug, Copy, Clone)]
+pub struct TableReport {
+    /// Number of texels with an infinite or NaN component
+    pub non_finite: u32,
+    /// Number of texels with a negative component
+    pub negative: u32,
+    pub first_non_finite: Option<Texel>,
+    pub first_negative: Option<Texel>,
+}
+
+impl TableReport {
+    /// `has` states whether mu, mu_s and nu parameterize the table
+    fn new(raw: &[InvalidRaw; 2], size: [u32; 3], has: [bool; 3]) -> Self {
+        let texel = |x: &InvalidRaw| {
+            if x.count == 0 {
+                return None;
+            }
+            let pick = |has: bool, value: f32| if has { Some(value) } else { None };
+            Some(Texel {
+                coords: [
+                    x.first % size[0],
+                    x.first / size[0] % size[1],
+                    x.first / (size[0] * size[1]),
+                ],
+                r: x.coords[0],
+                mu: pick(has[0], x.coords[1]),
+                mu_s: pick(has[1], x.coords[2]),
+                nu: pick(has[2], x.coords[3]),
+            })
+        };
+        Self {
+            non_finite: raw[0].count,
+            negative: raw[1].count,
+            first_non_finite: texel(&raw[0]),
+            first_negative: texel(&raw[1]),
+        }
+    }
+}
+
+/// A look-up table texel, and the atmosphere parameters it corresponds to
+///
+/// Parameters that the table doesn't vary over are `None`.
+#[derive(Debug, Copy, Clone)]
+pub struct Texel {
+    /// Position within the table, with z always 0 for 2D tables
+    pub coords: [u32; 3],
+    /// Distance from the planet's center
+    pub r: f32,
+    /// Cosine of the view zenith angle
+    pub mu: Option<f32>,
+    /// Cosine of the sun zenith angle
+    pub mu_s: Option<f32>,
+    /// Cosine of the angle between the view and sun directions
+    pub nu: Option<f32>,
+}
+
+/// Intermediate results of one scattering order, as returned by `PendingAtmosphere::order_exports`
+/// starting from the first
+///
+/// Each table holds texels in row-major order, with the extent and encoding of the corresponding
+/// `Atmosphere` look-up table. Orders past `Atmosphere::scattering_order` aren't computed, so hold
+/// undefined values.
+#[derive(Debug, Clone)]
+pub struct OrderExport {
+    /// Ground irradiance due to the previous order, or to direct sunlight for the first order
+    pub irradiance: Vec<[f32; 4]>,
+    /// Light scattered exactly this many times, excluding Mie single scattering
+    pub scattering: Vec<[f32; 4]>,
+    /// Mie single scattering, present for the first order only
+    pub mie: Option<Vec<[f32; 4]>>,
+    /// Light scattered by the previous order, from which this one was integrated; absent for the
+    /// first order
+    pub scattering_density: Option<Vec<[f32; 4]>>,
+}
+
+/// GPU time spent computing one scattering order
+#[derive(Debug, Copy, Clone)]
+pub struct OrderTimings {
+    pub scattering_density: Duration,
+    pub indirect_irradiance: Duration,
+    /// Includes measuring convergence if `Parameters::convergence_threshold` was set
+    pub multiple_scattering: Duration,
+}
+
+impl OrderTimings {
+    pub fn total(&self) -> Duration {
+        self.scattering_density + self.indirect_irradiance + self.multiple_scattering
+    }
+}
+
+/// Offsets of the two scattering-sized tables that follow delta irradiance in an `Export`, and its
+/// total size
+fn export_offsets(irradiance: vk::Extent2D, scattering: vk::Extent3D) -> [vk::DeviceSize; 3] {
+    let irradiance_size = vk::DeviceSize::from(irradiance.width * irradiance.height)
+        * mem::size_of::<[f32; 4]>() as vk::DeviceSize;
+    let scattering_size =
+        vk::DeviceSize::from(scattering.width * scattering.height * scattering.depth)
+            * mem::size_of::<[u16; 4]>() as vk::DeviceSize;
+    [
+        irradiance_size,
+        irradiance_size + scattering_size,
+        irradiance_size + 2 * scattering_size,
+    ]
+}
+
+/// Decode an IEEE 754 half-precision float, the component type of the scattering tables
+fn f16_to_f32(x: u16) -> f32 {
+    let exponent = u32::from(x >> 10) & 0x1f;
+    let mantissa = u32::from(x & 0x3ff);
+    let magnitude = match exponent {
+        // Zero or subnormal
+        0 => mantissa as f32 * 2f32.powi(-24),
+        // Infinity or NaN
+        0x1f => f32::from_bits(0x7f80_0000 | mantissa << 13),
+        _ => f32::from_bits((exponent + 112) << 23 | mantissa << 13),
+    };
+    if x & 0x8000 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+fn step_count(order: u32) -> u32 {
+    4 + 3 * order.saturating_sub(1)
+}
+
 fn find_memory_type(
     device_props: &vk::PhysicalDeviceMemoryProperties,
     type_bits: u32,
@@ -2228,7 +4629,28 @@ fn find_memory_type(
     None
 }
 
-unsafe fn allocate(
+/// Place resources consecutively in a single allocation, returning each one's offset and the
+/// requirements of the whole
+fn pack(reqs: &[vk::MemoryRequirements]) -> (Vec<vk::DeviceSize>, vk::MemoryRequirements) {
+    let mut total = vk::MemoryRequirements {
+        size: 0,
+        alignment: 1,
+        memory_type_bits: !0,
+    };
+    let offsets = reqs
+        .iter()
+        .map(|x| {
+            let offset = total.size.next_multiple_of(x.alignment);
+            total.size = offset + x.size;
+            total.alignment = total.alignment.max(x.alignment);
+            total.memory_type_bits &= x.memory_type_bits;
+            offset
+        })
+        .collect();
+    (offsets, total)
+}
+
+pub(crate) unsafe fn allocate(
     device: &Device,
     device_props: &vk::PhysicalDeviceMemoryProperties,
     reqs: vk::MemoryRequirements,
@@ -2249,5 +4671,116 @@ unsafe fn allocate(
     )
 }
 
-const WORKGROUP_SIZE_2D: u32 = 8;
-const WORKGROUP_SIZE_3D: u32 = 4;
+/// View the memory of `x` as bytes
+pub(crate) fn as_bytes<T>(x: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(x as *const T as *const u8, mem::size_of::<T>()) }
+}
+
+/// Constant, vertical, and sunward terms of the directional irradiance LUT
+const DIRECTIONAL_IRRADIANCE_LAYERS: u32 = 3;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_shaders_match_interface() {
+        let sampler = vk::Sampler::null();
+        let quality = Quality::default();
+        let (size_2d, size_3d) = (8, 4);
+        let params_bindings = params_bindings();
+        let check = |name,
+                     code,
+                     bindings: &[vk::DescriptorSetLayoutBinding],
+                     push_constant_size,
+                     local_size,
+                     specialization: &[u32]| {
+            shader::validate(
+                code,
+                code,
+                &Interface {
+                    name,
+                    stage: vk::ShaderStageFlags::COMPUTE,
+                    sets: &[&params_bindings, bindings],
+                    push_constant_size,
+                    local_size,
+                    specialization,
+                },
+            );
+        };
+        check(
+            "transmittance",
+            TRANSMITTANCE,
+            &transmittance_bindings(),
+            0,
+            Some([size_2d, size_2d, 1]),
+            &[quality.transmittance_samples, size_2d, size_2d, 1],
+        );
+        check(
+            "direct irradiance",
+            DIRECT_IRRADIANCE,
+            &direct_irradiance_bindings(&sampler),
+            0,
+            Some([size_2d, size_2d, 1]),
+            &[0, size_2d, size_2d, 1],
+        );
+        check(
+            "indirect irradiance",
+            INDIRECT_IRRADIANCE,
+            &indirect_irradiance_bindings(&sampler),
+            4,
+            Some([size_2d, size_2d, 1]),
+            &[quality.indirect_irradiance_samples, size_2d, size_2d, 1],
+        );
+        check(
+            "single scattering",
+            SINGLE_SCATTERING,
+            &scattering_bindings(&sampler),
+            0,
+            Some([size_3d; 3]),
+            &[quality.single_scattering_samples, size_3d, size_3d, size_3d],
+        );
+        check(
+            "scattering density",
+            SCATTERING_DENSITY,
+            &scattering_density_bindings(&sampler),
+            4,
+            Some([size_3d; 3]),
+            &[
+                quality.scattering_density_samples,
+                size_3d,
+                size_3d,
+                size_3d,
+            ],
+        );
+        check(
+            "multiple scattering",
+            MULTIPLE_SCATTERING,
+            &multiple_scattering_bindings(&sampler),
+            4,
+            Some([size_3d; 3]),
+            &[
+                quality.multiple_scattering_samples,
+                size_3d,
+                size_3d,
+                size_3d,
+            ],
+        );
+        check(
+            "convergence",
+            CONVERGENCE,
+            &convergence_bindings(),
+            8,
+            Some([CONVERGENCE_WORKGROUP_SIZE, 1, 1]),
+            &[],
+        );
+        check(
+            "validate_luts",
+            VALIDATE_LUTS,
+            &validation_bindings(&sampler),
+            4,
+            None,
+            &[],
+        );
+    }
+}
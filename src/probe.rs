@@ -0,0 +1,697 @@
+use std::{mem, ptr, sync::Arc};
+
+use ash::version::DeviceV1_0;
+use ash::{vk, Device};
+use vk_shader_macros::include_glsl;
+
+use crate::precompute::allocate;
+use crate::{Atmosphere, Builder};
+
+const PROBE_SKY: &[u32] = include_glsl!("shaders/probe_sky.comp");
+const PROBE_PREFILTER: &[u32] = include_glsl!("shaders/probe_prefilter.comp");
+const PROBE_IRRADIANCE: &[u32] = include_glsl!("shaders/probe_irradiance.comp");
+
+/// Renders the sky into cube maps for image-based lighting
+pub struct ProbeRenderer {
+    device: Arc<Device>,
+    memory_props: vk::PhysicalDeviceMemoryProperties,
+    sampler: vk::Sampler,
+    sky: Stage,
+    prefilter: Stage,
+    irradiance: Stage,
+}
+
+impl Drop for ProbeRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_sampler(self.sampler, None);
+            for stage in &[&self.sky, &self.prefilter, &self.irradiance] {
+                self.device.destroy_pipeline(stage.pipeline, None);
+                self.device.destroy_pipeline_layout(stage.layout, None);
+                self.device
+                    .destroy_descriptor_set_layout(stage.ds_layout, None);
+            }
+        }
+    }
+}
+
+impl ProbeRenderer {
+    /// Construct a renderer that fills probes using compute shaders
+    ///
+    /// No device features are required, but commands must be recorded for a queue family that
+    /// supports compute. Probe images need `STORAGE` and `SAMPLED` usage, as described by
+    /// `create_probe`, and atmospheres rendered from must be built with a
+    /// `Parameters::dst_stage_mask` that includes `COMPUTE_SHADER`.
+    pub fn new(builder: &Builder, cache: vk::PipelineCache) -> Self {
+        let device = builder.device().clone();
+        unsafe {
+            // Trilinear, for filtered importance sampling
+            let sampler = device
+                .create_sampler(
+                    &vk::SamplerCreateInfo {
+                        min_filter: vk::Filter::LINEAR,
+                        mag_filter: vk::Filter::LINEAR,
+                        mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+                        address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                        address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                        address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                        max_lod: vk::LOD_CLAMP_NONE,
+                        ..Default::default()
+                    },
+                    None,
+                )
+                .unwrap();
+
+            let sky_ds_layout = device
+                .create_descriptor_set_layout(
+                    &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&[
+                        // probe
+                        vk::DescriptorSetLayoutBinding {
+                            binding: 0,
+                            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                            descriptor_count: 1,
+                            stage_flags: vk::ShaderStageFlags::COMPUTE,
+                            p_immutable_samplers: ptr::null(),
+                        },
+                    ]),
+                    None,
+                )
+                .unwrap();
+
+            let prefilter_ds_layout = device
+                .create_descriptor_set_layout(
+                    &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&[
+                        // source
+                        vk::DescriptorSetLayoutBinding {
+                            binding: 0,
+                            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                            descriptor_count: 1,
+                            stage_flags: vk::ShaderStageFlags::COMPUTE,
+                            p_immutable_samplers: &sampler,
+                        },
+                        // destination
+                        vk::DescriptorSetLayoutBinding {
+                            binding: 1,
+                            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                            descriptor_count: 1,
+                            stage_flags: vk::ShaderStageFlags::COMPUTE,
+                            p_immutable_samplers: ptr::null(),
+                        },
+                    ]),
+                    None,
+                )
+                .unwrap();
+
+            let irradiance_ds_layout = device
+                .create_descriptor_set_layout(
+                    &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&[
+                        // source
+                        vk::DescriptorSetLayoutBinding {
+                            binding: 0,
+                            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                            descriptor_count: 1,
+                            stage_flags: vk::ShaderStageFlags::COMPUTE,
+                            p_immutable_samplers: &sampler,
+                        },
+                        // irradiance
+                        vk::DescriptorSetLayoutBinding {
+                            binding: 1,
+                            descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                            descriptor_count: 1,
+                            stage_flags: vk::ShaderStageFlags::COMPUTE,
+                            p_immutable_samplers: ptr::null(),
+                        },
+                    ]),
+                    None,
+                )
+                .unwrap();
+
+            let sky_layout = device
+                .create_pipeline_layout(
+                    &vk::PipelineLayoutCreateInfo::builder()
+                        .set_layouts(&[builder.render_ds_layout(), sky_ds_layout])
+                        .push_constant_ranges(&[vk::PushConstantRange {
+                            stage_flags: vk::ShaderStageFlags::COMPUTE,
+                            offset: 0,
+                            size: mem::size_of::<SkyParamsRaw>() as u32,
+                        }]),
+                    None,
+                )
+                .unwrap();
+
+            let prefilter_layout = device
+                .create_pipeline_layout(
+                    &vk::PipelineLayoutCreateInfo::builder()
+                        .set_layouts(&[prefilter_ds_layout])
+                        .push_constant_ranges(&[vk::PushConstantRange {
+                            stage_flags: vk::ShaderStageFlags::COMPUTE,
+                            offset: 0,
+                            size: 4,
+                        }]),
+                    None,
+                )
+                .unwrap();
+
+            let irradiance_layout = device
+                .create_pipeline_layout(
+                    &vk::PipelineLayoutCreateInfo::builder().set_layouts(&[irradiance_ds_layout]),
+                    None,
+                )
+                .unwrap();
+
+            let shaders = [PROBE_SKY, PROBE_PREFILTER, PROBE_IRRADIANCE]
+                .iter()
+                .map(|code| {
+                    device
+                        .create_shader_module(
+                            &vk::ShaderModuleCreateInfo::builder().code(code),
+                            None,
+                        )
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+
+            let p_name = b"main\0".as_ptr() as *const i8;
+            let mut pipelines = device
+                .create_compute_pipelines(
+                    cache,
+                    &[
+                        (shaders[0], sky_layout),
+                        (shaders[1], prefilter_layout),
+                        (shaders[2], irradiance_layout),
+                    ]
+                    .iter()
+                    .map(|&(module, layout)| vk::ComputePipelineCreateInfo {
+                        stage: vk::PipelineShaderStageCreateInfo {
+                            stage: vk::ShaderStageFlags::COMPUTE,
+                            module,
+                            p_name,
+                            ..Default::default()
+                        },
+                        layout,
+                        ..Default::default()
+                    })
+                    .collect::<Vec<_>>(),
+                    None,
+                )
+                .unwrap()
+                .into_iter();
+
+            for &shader in &shaders {
+                device.destroy_shader_module(shader, None);
+            }
+
+            let sky = Stage {
+                pipeline: pipelines.next().unwrap(),
+                layout: sky_layout,
+                ds_layout: sky_ds_layout,
+            };
+            let prefilter = Stage {
+                pipeline: pipelines.next().unwrap(),
+                layout: prefilter_layout,
+                ds_layout: prefilter_ds_layout,
+            };
+            let irradiance = Stage {
+                pipeline: pipelines.next().unwrap(),
+                layout: irradiance_layout,
+                ds_layout: irradiance_ds_layout,
+            };
+            debug_assert!(pipelines.next().is_none());
+
+            Self {
+                device,
+                memory_props: *builder.memory_props(),
+                sampler,
+                sky,
+                prefilter,
+                irradiance,
+            }
+        }
+    }
+
+    /// Prepare to render into `image`
+    ///
+    /// `image` must be a `size` by `size` `R16G16B16A16_SFLOAT` image with six array layers and
+    /// `mip_levels` levels, created with `CUBE_COMPATIBLE`, and with at least `STORAGE` and
+    /// `SAMPLED` usage. Level 0 receives the sky's radiance, and each subsequent level is
+    /// prefiltered for GGX specular reflections with roughness increasing linearly to 1 at the last
+    /// level. If `irradiance` is set, spherical harmonic coefficients for irradiance are also
+    /// computed; see `Probe::irradiance_buffer`.
+    ///
+    /// `image` must outlive the returned `Probe`.
+    pub unsafe fn create_probe(
+        &self,
+        image: vk::Image,
+        size: u32,
+        mip_levels: u32,
+        irradiance: bool,
+    ) -> Probe {
+        assert!(mip_levels >= 1, "a probe needs at least one mip level");
+        let device = &*self.device;
+        let view = |ty, base_mip_level, level_count| {
+            device
+                .create_image_view(
+                    &vk::ImageViewCreateInfo {
+                        image,
+                        view_type: ty,
+                        format: FORMAT,
+                        components: vk::ComponentMapping::default(),
+                        subresource_range: vk::ImageSubresourceRange {
+                            aspect_mask: vk::ImageAspectFlags::COLOR,
+                            base_mip_level,
+                            level_count,
+                            base_array_layer: 0,
+                            layer_count: 6,
+                        },
+                        ..Default::default()
+                    },
+                    None,
+                )
+                .unwrap()
+        };
+        // One per level, for writing
+        let storage_views = (0..mip_levels)
+            .map(|level| view(vk::ImageViewType::TYPE_2D_ARRAY, level, 1))
+            .collect::<Vec<_>>();
+        // The levels preceding each prefiltered level, for reading
+        let source_views = (1..mip_levels.max(2))
+            .map(|levels| view(vk::ImageViewType::CUBE, 0, levels))
+            .collect::<Vec<_>>();
+
+        let prefilter_count = mip_levels - 1;
+        let set_count = 1 + prefilter_count + irradiance as u32;
+        let descriptor_pool = device
+            .create_descriptor_pool(
+                &vk::DescriptorPoolCreateInfo::builder()
+                    .max_sets(set_count)
+                    .pool_sizes(&[
+                        vk::DescriptorPoolSize {
+                            ty: vk::DescriptorType::STORAGE_IMAGE,
+                            descriptor_count: mip_levels,
+                        },
+                        vk::DescriptorPoolSize {
+                            ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                            descriptor_count: prefilter_count + 1,
+                        },
+                        vk::DescriptorPoolSize {
+                            ty: vk::DescriptorType::STORAGE_BUFFER,
+                            descriptor_count: 1,
+                        },
+                    ]),
+                None,
+            )
+            .unwrap();
+        let mut set_layouts = vec![self.sky.ds_layout];
+        set_layouts.extend((0..prefilter_count).map(|_| self.prefilter.ds_layout));
+        if irradiance {
+            set_layouts.push(self.irradiance.ds_layout);
+        }
+        let mut sets = device
+            .allocate_descriptor_sets(
+                &vk::DescriptorSetAllocateInfo::builder()
+                    .descriptor_pool(descriptor_pool)
+                    .set_layouts(&set_layouts),
+            )
+            .unwrap()
+            .into_iter();
+        let sky_ds = sets.next().unwrap();
+        let prefilter_ds = sets
+            .by_ref()
+            .take(prefilter_count as usize)
+            .collect::<Vec<_>>();
+
+        let storage_infos = storage_views
+            .iter()
+            .map(|&image_view| vk::DescriptorImageInfo {
+                sampler: vk::Sampler::null(),
+                image_view,
+                image_layout: vk::ImageLayout::GENERAL,
+            })
+            .collect::<Vec<_>>();
+        let source_infos = source_views
+            .iter()
+            .map(|&image_view| vk::DescriptorImageInfo {
+                sampler: vk::Sampler::null(),
+                image_view,
+                image_layout: vk::ImageLayout::GENERAL,
+            })
+            .collect::<Vec<_>>();
+        let mut writes = vec![vk::WriteDescriptorSet {
+            dst_set: sky_ds,
+            dst_binding: 0,
+            dst_array_element: 0,
+            descriptor_count: 1,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            p_image_info: &storage_infos[0],
+            ..Default::default()
+        }];
+        for (i, &ds) in prefilter_ds.iter().enumerate() {
+            writes.push(vk::WriteDescriptorSet {
+                dst_set: ds,
+                dst_binding: 0,
+                dst_array_element: 0,
+                descriptor_count: 1,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                p_image_info: &source_infos[i],
+                ..Default::default()
+            });
+            writes.push(vk::WriteDescriptorSet {
+                dst_set: ds,
+                dst_binding: 1,
+                dst_array_element: 0,
+                descriptor_count: 1,
+                descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                p_image_info: &storage_infos[i + 1],
+                ..Default::default()
+            });
+        }
+
+        let irradiance = if irradiance {
+            let ds = sets.next().unwrap();
+            let buffer = device
+                .create_buffer(
+                    &vk::BufferCreateInfo {
+                        size: IRRADIANCE_SIZE,
+                        usage: vk::BufferUsageFlags::STORAGE_BUFFER
+                            | vk::BufferUsageFlags::UNIFORM_BUFFER
+                            | vk::BufferUsageFlags::TRANSFER_SRC,
+                        ..Default::default()
+                    },
+                    None,
+                )
+                .unwrap();
+            let memory = {
+                let reqs = device.get_buffer_memory_requirements(buffer);
+                allocate(
+                    device,
+                    &self.memory_props,
+                    reqs,
+                    vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                )
+                .unwrap()
+            };
+            device.bind_buffer_memory(buffer, memory, 0).unwrap();
+            Some(Irradiance { buffer, memory, ds })
+        } else {
+            None
+        };
+        let irradiance_buffer_info = irradiance.as_ref().map(|x| vk::DescriptorBufferInfo {
+            buffer: x.buffer,
+            offset: 0,
+            range: IRRADIANCE_SIZE,
+        });
+        if let (Some(x), Some(buffer_info)) = (&irradiance, &irradiance_buffer_info) {
+            writes.push(vk::WriteDescriptorSet {
+                dst_set: x.ds,
+                dst_binding: 0,
+                dst_array_element: 0,
+                descriptor_count: 1,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                p_image_info: &source_infos[0],
+                ..Default::default()
+            });
+            writes.push(vk::WriteDescriptorSet {
+                dst_set: x.ds,
+                dst_binding: 1,
+                dst_array_element: 0,
+                descriptor_count: 1,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                p_buffer_info: buffer_info,
+                ..Default::default()
+            });
+        }
+        device.update_descriptor_sets(&writes, &[]);
+
+        Probe {
+            device: self.device.clone(),
+            image,
+            size,
+            mip_levels,
+            storage_views,
+            source_views,
+            descriptor_pool,
+            sky_ds,
+            prefilter_ds,
+            irradiance,
+        }
+    }
+
+    /// Record commands to render the sky as seen from `params.position` into `probe`
+    ///
+    /// The previous contents of the probe are discarded. The atmosphere's look-up tables must be
+    /// ready for use by compute shaders.
+    pub fn render(
+        &self,
+        cmd: vk::CommandBuffer,
+        atmosphere: &Atmosphere,
+        probe: &Probe,
+        params: &ProbeParameters,
+    ) {
+        let device = &*self.device;
+        let groups = |size: u32| size.div_ceil(PROBE_WORKGROUP_SIZE);
+        let level_barrier = |level: u32| vk::ImageMemoryBarrier {
+            src_access_mask: vk::AccessFlags::SHADER_WRITE,
+            dst_access_mask: vk::AccessFlags::SHADER_READ,
+            old_layout: vk::ImageLayout::GENERAL,
+            new_layout: vk::ImageLayout::GENERAL,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image: probe.image,
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: level,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 6,
+            },
+            ..Default::default()
+        };
+        unsafe {
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                Default::default(),
+                &[],
+                &[],
+                &[vk::ImageMemoryBarrier {
+                    src_access_mask: vk::AccessFlags::empty(),
+                    dst_access_mask: vk::AccessFlags::SHADER_WRITE,
+                    old_layout: vk::ImageLayout::UNDEFINED,
+                    new_layout: vk::ImageLayout::GENERAL,
+                    subresource_range: vk::ImageSubresourceRange {
+                        level_count: probe.mip_levels,
+                        ..level_barrier(0).subresource_range
+                    },
+                    ..level_barrier(0)
+                }],
+            );
+
+            device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::COMPUTE, self.sky.pipeline);
+            device.cmd_bind_descriptor_sets(
+                cmd,
+                vk::PipelineBindPoint::COMPUTE,
+                self.sky.layout,
+                0,
+                &[atmosphere.descriptor_set(), probe.sky_ds],
+                &[],
+            );
+            device.cmd_push_constants(
+                cmd,
+                self.sky.layout,
+                vk::ShaderStageFlags::COMPUTE,
+                0,
+                &mem::transmute::<_, [u8; 44]>(SkyParamsRaw::new(params)),
+            );
+            device.cmd_dispatch(cmd, groups(probe.size), groups(probe.size), 6);
+
+            device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::COMPUTE, self.prefilter.pipeline);
+            for (i, &ds) in probe.prefilter_ds.iter().enumerate() {
+                let level = i as u32 + 1;
+                device.cmd_pipeline_barrier(
+                    cmd,
+                    vk::PipelineStageFlags::COMPUTE_SHADER,
+                    vk::PipelineStageFlags::COMPUTE_SHADER,
+                    Default::default(),
+                    &[],
+                    &[],
+                    &[level_barrier(level - 1)],
+                );
+                device.cmd_bind_descriptor_sets(
+                    cmd,
+                    vk::PipelineBindPoint::COMPUTE,
+                    self.prefilter.layout,
+                    0,
+                    &[ds],
+                    &[],
+                );
+                let roughness = level as f32 / (probe.mip_levels - 1) as f32;
+                device.cmd_push_constants(
+                    cmd,
+                    self.prefilter.layout,
+                    vk::ShaderStageFlags::COMPUTE,
+                    0,
+                    &roughness.to_ne_bytes(),
+                );
+                let size = (probe.size >> level).max(1);
+                device.cmd_dispatch(cmd, groups(size), groups(size), 6);
+            }
+
+            // Levels aren't read after they're written, except by the above
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                Default::default(),
+                &[],
+                &[],
+                &[level_barrier(probe.mip_levels - 1)],
+            );
+
+            let mut buffer_barriers = Vec::new();
+            if let Some(ref irradiance) = probe.irradiance {
+                device.cmd_bind_pipeline(
+                    cmd,
+                    vk::PipelineBindPoint::COMPUTE,
+                    self.irradiance.pipeline,
+                );
+                device.cmd_bind_descriptor_sets(
+                    cmd,
+                    vk::PipelineBindPoint::COMPUTE,
+                    self.irradiance.layout,
+                    0,
+                    &[irradiance.ds],
+                    &[],
+                );
+                device.cmd_dispatch(cmd, 1, 1, 1);
+                buffer_barriers.push(vk::BufferMemoryBarrier {
+                    src_access_mask: vk::AccessFlags::SHADER_WRITE,
+                    dst_access_mask: params.dst_access_mask,
+                    src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                    dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                    buffer: irradiance.buffer,
+                    offset: 0,
+                    size: vk::WHOLE_SIZE,
+                    ..Default::default()
+                });
+            }
+
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                params.dst_stage_mask,
+                Default::default(),
+                &[],
+                &buffer_barriers,
+                &[vk::ImageMemoryBarrier {
+                    src_access_mask: vk::AccessFlags::SHADER_WRITE,
+                    dst_access_mask: params.dst_access_mask,
+                    old_layout: vk::ImageLayout::GENERAL,
+                    new_layout: params.layout,
+                    subresource_range: vk::ImageSubresourceRange {
+                        level_count: probe.mip_levels,
+                        ..level_barrier(0).subresource_range
+                    },
+                    ..level_barrier(0)
+                }],
+            );
+        }
+    }
+}
+
+/// A cube image prepared for rendering by a `ProbeRenderer`
+pub struct Probe {
+    device: Arc<Device>,
+    image: vk::Image,
+    size: u32,
+    mip_levels: u32,
+    storage_views: Vec<vk::ImageView>,
+    source_views: Vec<vk::ImageView>,
+    descriptor_pool: vk::DescriptorPool,
+    sky_ds: vk::DescriptorSet,
+    prefilter_ds: Vec<vk::DescriptorSet>,
+    irradiance: Option<Irradiance>,
+}
+
+impl Drop for Probe {
+    fn drop(&mut self) {
+        unsafe {
+            self.device
+                .destroy_descriptor_pool(self.descriptor_pool, None);
+            for &view in self.storage_views.iter().chain(&self.source_views) {
+                self.device.destroy_image_view(view, None);
+            }
+            if let Some(ref irradiance) = self.irradiance {
+                self.device.destroy_buffer(irradiance.buffer, None);
+                self.device.free_memory(irradiance.memory, None);
+            }
+        }
+    }
+}
+
+impl Probe {
+    /// Buffer containing spherical harmonic coefficients for irradiance, if requested
+    ///
+    /// Holds nine `vec4`s, whose first three components are the RGB coefficients of the real
+    /// spherical harmonic basis functions in the order (l, m) = (0, 0), (1, -1), (1, 0), (1, 1),
+    /// (2, -2), (2, -1), (2, 0), (2, 1), (2, 2), where the l = 1 functions are proportional to y,
+    /// z, and x respectively. They are already convolved with the clamped cosine, so irradiance
+    /// on a surface with normal n is the sum of each coefficient times its basis function at n.
+    pub fn irradiance_buffer(&self) -> Option<vk::Buffer> {
+        self.irradiance.as_ref().map(|x| x.buffer)
+    }
+}
+
+struct Irradiance {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    ds: vk::DescriptorSet,
+}
+
+struct Stage {
+    pipeline: vk::Pipeline,
+    layout: vk::PipelineLayout,
+    ds_layout: vk::DescriptorSetLayout,
+}
+
+/// Parameters for rendering a probe
+#[derive(Debug, Copy, Clone)]
+pub struct ProbeParameters {
+    /// Position to render from, in the planet's reference frame as for `DrawParameters`
+    pub position: [f32; 3],
+    pub sun_direction: [f32; 3],
+    /// As for `DrawParameters`
+    pub solar_irradiance: [f32; 3],
+    /// Stage mask for synchronizing with subsequent use of the probe
+    pub dst_stage_mask: vk::PipelineStageFlags,
+    /// Access mask for synchronizing with subsequent use of the probe
+    pub dst_access_mask: vk::AccessFlags,
+    /// Layout the probe image should end in
+    pub layout: vk::ImageLayout,
+}
+
+#[repr(C)]
+struct SkyParamsRaw {
+    position: [f32; 3],
+    _padding: u32,
+    sun_direction: [f32; 3],
+    _padding2: u32,
+    solar_irradiance: [f32; 3],
+}
+
+impl SkyParamsRaw {
+    fn new(x: &ProbeParameters) -> Self {
+        Self {
+            position: x.position,
+            _padding: 0,
+            sun_direction: x.sun_direction,
+            _padding2: 0,
+            solar_irradiance: x.solar_irradiance,
+        }
+    }
+}
+
+const FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+const IRRADIANCE_SIZE: vk::DeviceSize = 9 * 16;
+/// Must match `local_size` in `probe_sky.comp` and `probe_prefilter.comp`
+const PROBE_WORKGROUP_SIZE: u32 = 8;
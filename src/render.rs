@@ -1,55 +1,852 @@
-use std::{mem, sync::Arc};
+use std::{ffi::c_void, mem, ptr, sync::Arc};
 
 use ash::version::DeviceV1_0;
 use ash::{vk, Device};
 use vk_shader_macros::include_glsl;
 
+use crate::debug::DebugUtils;
+use crate::precompute::{self, allocate, as_bytes};
+use crate::shader::{self, Interface};
 use crate::{Atmosphere, Builder};
 
 const FULLSCREEN: &[u32] = include_glsl!("shaders/fullscreen.vert");
-const RENDER_SKY: &[u32] = include_glsl!("shaders/render_sky.frag");
+
+// Flags selecting shader variants, used to index the tables below
+const VARIANT_SAMPLED_DEPTH: usize = 1;
+const VARIANT_MULTISAMPLE: usize = 2;
+const VARIANT_MULTIVIEW: usize = 4;
+const VARIANT_SINGLE_SOURCE: usize = 8;
+
+const RENDER_BACKGROUND: [&[u32]; 8] = [
+    include_glsl!("shaders/render_background.frag"),
+    include_glsl!("shaders/render_background.frag", define: SAMPLED_DEPTH),
+    include_glsl!("shaders/render_background.frag", define: MULTISAMPLE),
+    include_glsl!(
+        "shaders/render_background.frag",
+        define: SAMPLED_DEPTH,
+        define: MULTISAMPLE
+    ),
+    include_glsl!("shaders/render_background.frag", define: MULTIVIEW),
+    include_glsl!(
+        "shaders/render_background.frag",
+        define: SAMPLED_DEPTH,
+        define: MULTIVIEW
+    ),
+    include_glsl!(
+        "shaders/render_background.frag",
+        define: MULTISAMPLE,
+        define: MULTIVIEW
+    ),
+    include_glsl!(
+        "shaders/render_background.frag",
+        define: SAMPLED_DEPTH,
+        define: MULTISAMPLE,
+        define: MULTIVIEW
+    ),
+];
+const RENDER_SKY: [&[u32]; 16] = [
+    include_glsl!("shaders/render_sky.frag"),
+    include_glsl!("shaders/render_sky.frag", define: SAMPLED_DEPTH),
+    include_glsl!("shaders/render_sky.frag", define: MULTISAMPLE),
+    include_glsl!(
+        "shaders/render_sky.frag",
+        define: SAMPLED_DEPTH,
+        define: MULTISAMPLE
+    ),
+    include_glsl!("shaders/render_sky.frag", define: MULTIVIEW),
+    include_glsl!(
+        "shaders/render_sky.frag",
+        define: SAMPLED_DEPTH,
+        define: MULTIVIEW
+    ),
+    include_glsl!(
+        "shaders/render_sky.frag",
+        define: MULTISAMPLE,
+        define: MULTIVIEW
+    ),
+    include_glsl!(
+        "shaders/render_sky.frag",
+        define: SAMPLED_DEPTH,
+        define: MULTISAMPLE,
+        define: MULTIVIEW
+    ),
+    include_glsl!("shaders/render_sky.frag", define: SINGLE_SOURCE),
+    include_glsl!(
+        "shaders/render_sky.frag",
+        define: SAMPLED_DEPTH,
+        define: SINGLE_SOURCE
+    ),
+    include_glsl!(
+        "shaders/render_sky.frag",
+        define: MULTISAMPLE,
+        define: SINGLE_SOURCE
+    ),
+    include_glsl!(
+        "shaders/render_sky.frag",
+        define: SAMPLED_DEPTH,
+        define: MULTISAMPLE,
+        define: SINGLE_SOURCE
+    ),
+    include_glsl!(
+        "shaders/render_sky.frag",
+        define: MULTIVIEW,
+        define: SINGLE_SOURCE
+    ),
+    include_glsl!(
+        "shaders/render_sky.frag",
+        define: SAMPLED_DEPTH,
+        define: MULTIVIEW,
+        define: SINGLE_SOURCE
+    ),
+    include_glsl!(
+        "shaders/render_sky.frag",
+        define: MULTISAMPLE,
+        define: MULTIVIEW,
+        define: SINGLE_SOURCE
+    ),
+    include_glsl!(
+        "shaders/render_sky.frag",
+        define: SAMPLED_DEPTH,
+        define: MULTISAMPLE,
+        define: MULTIVIEW,
+        define: SINGLE_SOURCE
+    ),
+];
+const RENDER_SKY_COMPUTE: &[u32] = include_glsl!("shaders/render_sky.comp");
+const RENDER_SKY_COMPUTE_BACKGROUND: &[u32] =
+    include_glsl!("shaders/render_sky.comp", define: BACKGROUND);
 
 // TODO: Rasterize icospheres rather than raytracing
 pub struct Renderer {
     device: Arc<Device>,
+    frame_ds_layout: vk::DescriptorSetLayout,
+    depth_descriptor_type: vk::DescriptorType,
     pipeline_layout: vk::PipelineLayout,
-    pipeline: vk::Pipeline,
+    /// Drawn in order
+    sky_pipelines: Vec<vk::Pipeline>,
+    background_pipeline: vk::Pipeline,
     frame_pool: vk::DescriptorPool,
     frames: Vec<Frame>,
+    /// Present if multiview is enabled
+    views: Option<ViewBuffer>,
+    debug: Option<DebugUtils>,
 }
 
 impl Drop for Renderer {
     fn drop(&mut self) {
         unsafe {
+            if let Some(ref views) = self.views {
+                self.device.destroy_buffer(views.buffer, None);
+                self.device.free_memory(views.memory, None);
+            }
             self.device.destroy_descriptor_pool(self.frame_pool, None);
-            self.device.destroy_pipeline(self.pipeline, None);
+            for &pipeline in &self.sky_pipelines {
+                self.device.destroy_pipeline(pipeline, None);
+            }
+            self.device.destroy_pipeline(self.background_pipeline, None);
             self.device
                 .destroy_pipeline_layout(self.pipeline_layout, None);
+            self.device
+                .destroy_descriptor_set_layout(self.frame_ds_layout, None);
         }
     }
 }
 
 impl Renderer {
-    /// Construct an atmosphere renderer
+    /// Construct an atmosphere renderer for use in `subpass` of `render_pass`
+    ///
+    /// Depth is read from an input attachment of the subpass, set with `set_depth_buffer`. The sky
+    /// is blended into the subpass's first color attachment.
+    ///
+    /// If `options.dual_src_blend` is set, as by default, dual-source blending is used to draw the
+    /// sky in a single pass, which requires the `dualSrcBlend` feature. Leaving it unset costs a
+    /// second full-screen pass, ray-marching every sky pixel twice.
+    ///
+    /// `options.samples` must match the subpass's attachments. When multisampling, the atmosphere
+    /// is shaded per sample so that edges of geometry are accurately fogged, which requires the
+    /// `sampleRateShading` feature.
+    ///
+    /// `options.view_mask` must match the subpass's view mask when using `VK_KHR_multiview`. A
+    /// renderer with a nonzero view mask must be drawn with `draw_multiview`.
     pub fn new(
         builder: &Builder,
         cache: vk::PipelineCache,
         render_pass: vk::RenderPass,
         subpass: u32,
         frames: u32,
+        options: RendererOptions,
     ) -> Self {
-        let device = builder.device().clone();
         unsafe {
-            let vert = device
-                .create_shader_module(
-                    &vk::ShaderModuleCreateInfo::builder().code(&FULLSCREEN),
+            Self::with_target(
+                builder,
+                cache,
+                Target::Subpass {
+                    render_pass,
+                    subpass,
+                },
+                frames,
+                options,
+            )
+        }
+    }
+
+    /// Construct an atmosphere renderer for use with `VK_KHR_dynamic_rendering`
+    ///
+    /// `color_formats` and `depth_format` must match the attachments in use when drawing, with
+    /// `vk::Format::UNDEFINED` for an absent depth attachment. A `depth_format` with a stencil
+    /// aspect is also declared as the stencil attachment's format. The sky is blended into the
+    /// first color attachment, and other attachments are left untouched. Depth is read from a
+    /// sampled image set with `set_depth_buffer` rather than an input attachment, so the
+    /// `dynamicRendering` feature must be enabled but no render pass is required. Dual-source
+    /// blending, multisampling, and multiview are handled as in `new`. With multiview, the depth
+    /// image must have a layer for each view.
+    pub fn new_dynamic(
+        builder: &Builder,
+        cache: vk::PipelineCache,
+        color_formats: &[vk::Format],
+        depth_format: vk::Format,
+        frames: u32,
+        options: RendererOptions,
+    ) -> Self {
+        assert!(
+            !color_formats.is_empty(),
+            "the sky needs a color attachment to draw into"
+        );
+        unsafe {
+            Self::with_target(
+                builder,
+                cache,
+                Target::Dynamic {
+                    color_formats,
+                    depth_format,
+                },
+                frames,
+                options,
+            )
+        }
+    }
+
+    unsafe fn with_target(
+        builder: &Builder,
+        cache: vk::PipelineCache,
+        target: Target,
+        frames: u32,
+        options: RendererOptions,
+    ) -> Self {
+        let RendererOptions {
+            samples,
+            view_mask,
+            dual_src_blend,
+            shaders,
+        } = options;
+        let device = builder.device().clone();
+        let mut variant = 0;
+        let depth_descriptor_type = match target {
+            Target::Subpass { .. } => vk::DescriptorType::INPUT_ATTACHMENT,
+            Target::Dynamic { .. } => {
+                variant |= VARIANT_SAMPLED_DEPTH;
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER
+            }
+        };
+        if samples != vk::SampleCountFlags::TYPE_1 {
+            variant |= VARIANT_MULTISAMPLE;
+        }
+        let multiview = view_mask != 0;
+        if multiview {
+            variant |= VARIANT_MULTIVIEW;
+        }
+        let background_code = RENDER_BACKGROUND[variant];
+        if !dual_src_blend {
+            variant |= VARIANT_SINGLE_SOURCE;
+        }
+        let sky_code = RENDER_SKY[variant];
+
+        let sampler = builder.sampler();
+        let frame_bindings = frame_bindings(depth_descriptor_type, &sampler);
+        // Views are only bound with multiview
+        let frame_bindings = &frame_bindings[..if multiview { 3 } else { 2 }];
+        let frame_ds_layout = device
+            .create_descriptor_set_layout(
+                &vk::DescriptorSetLayoutCreateInfo::builder().bindings(frame_bindings),
+                None,
+            )
+            .unwrap();
+
+        let pipeline_layout = device
+            .create_pipeline_layout(
+                &vk::PipelineLayoutCreateInfo::builder()
+                    .set_layouts(&[
+                        builder.render_ds_layout(),
+                        frame_ds_layout,
+                        builder.render_ds_layout(),
+                    ])
+                    .push_constant_ranges(&[vk::PushConstantRange {
+                        stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                        offset: 0,
+                        size: mem::size_of::<DrawParamsRaw>() as u32,
+                    }]),
+                None,
+            )
+            .unwrap();
+
+        let render_bindings = precompute::render_bindings(&sampler);
+        let sets: [&[vk::DescriptorSetLayoutBinding]; 3] =
+            [&render_bindings, frame_bindings, &render_bindings];
+        let vert = shader::create_module(
+            &device,
+            FULLSCREEN,
+            shaders.vertex,
+            &Interface {
+                name: "vertex",
+                stage: vk::ShaderStageFlags::VERTEX,
+                sets: &sets,
+                push_constant_size: 0,
+                local_size: None,
+                specialization: &[],
+            },
+        );
+        let fragment_interface = |name| Interface {
+            name,
+            stage: vk::ShaderStageFlags::FRAGMENT,
+            sets: &sets,
+            push_constant_size: mem::size_of::<DrawParamsRaw>() as u32,
+            local_size: None,
+            specialization: &[],
+        };
+        let frag =
+            shader::create_module(&device, sky_code, shaders.sky, &fragment_interface("sky"));
+        let background_frag = shader::create_module(
+            &device,
+            background_code,
+            shaders.background,
+            &fragment_interface("background"),
+        );
+
+        let entry_point = b"main\0".as_ptr() as *const i8;
+        let noop_stencil_state = vk::StencilOpState {
+            fail_op: vk::StencilOp::KEEP,
+            pass_op: vk::StencilOp::KEEP,
+            depth_fail_op: vk::StencilOp::KEEP,
+            compare_op: vk::CompareOp::ALWAYS,
+            compare_mask: 0,
+            write_mask: 0,
+            reference: 0,
+        };
+        // Selects the output of the single-source sky shader
+        let transmittance_pass_entry = [vk::SpecializationMapEntry {
+            constant_id: 0,
+            offset: 0,
+            size: mem::size_of::<vk::Bool32>(),
+        }];
+        let inscatter_pass_specialization = vk::SpecializationInfo::builder()
+            .map_entries(&transmittance_pass_entry)
+            .data(as_bytes(&vk::FALSE));
+        let transmittance_pass_specialization = vk::SpecializationInfo::builder()
+            .map_entries(&transmittance_pass_entry)
+            .data(as_bytes(&vk::TRUE));
+        let sky_stages = [
+            vk::PipelineShaderStageCreateInfo {
+                stage: vk::ShaderStageFlags::VERTEX,
+                module: vert,
+                p_name: entry_point,
+                ..Default::default()
+            },
+            vk::PipelineShaderStageCreateInfo {
+                stage: vk::ShaderStageFlags::FRAGMENT,
+                module: frag,
+                p_name: entry_point,
+                p_specialization_info: if dual_src_blend {
+                    ptr::null()
+                } else {
+                    &*inscatter_pass_specialization
+                },
+                ..Default::default()
+            },
+        ];
+        let transmittance_stages = [
+            sky_stages[0],
+            vk::PipelineShaderStageCreateInfo {
+                p_specialization_info: &*transmittance_pass_specialization,
+                ..sky_stages[1]
+            },
+        ];
+        let background_stages = [
+            sky_stages[0],
+            vk::PipelineShaderStageCreateInfo {
+                stage: vk::ShaderStageFlags::FRAGMENT,
+                module: background_frag,
+                p_name: entry_point,
+                ..Default::default()
+            },
+        ];
+        let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::default();
+        let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+        let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+            .scissor_count(1)
+            .viewport_count(1);
+        let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
+            .cull_mode(vk::CullModeFlags::NONE)
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(1.0);
+        let multisample_state =
+            vk::PipelineMultisampleStateCreateInfo::builder().rasterization_samples(samples);
+        let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
+            .depth_test_enable(false)
+            .front(noop_stencil_state)
+            .back(noop_stencil_state);
+        let dynamic_state = vk::PipelineDynamicStateCreateInfo::builder()
+            .dynamic_states(&[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR]);
+
+        // Attachments after the first are left untouched
+        let color_attachment_count = match target {
+            Target::Subpass { .. } => 1,
+            Target::Dynamic { color_formats, .. } => color_formats.len(),
+        };
+        let untouched = vk::PipelineColorBlendAttachmentState {
+            color_write_mask: vk::ColorComponentFlags::empty(),
+            ..Default::default()
+        };
+        let mut sky_blend = vec![untouched; color_attachment_count];
+        sky_blend[0] = vk::PipelineColorBlendAttachmentState {
+            blend_enable: vk::TRUE,
+            src_color_blend_factor: vk::BlendFactor::ONE,
+            dst_color_blend_factor: if dual_src_blend {
+                vk::BlendFactor::SRC1_COLOR
+            } else {
+                vk::BlendFactor::ONE
+            },
+            color_blend_op: vk::BlendOp::ADD,
+            src_alpha_blend_factor: vk::BlendFactor::ZERO,
+            dst_alpha_blend_factor: vk::BlendFactor::ONE,
+            alpha_blend_op: vk::BlendOp::ADD,
+            color_write_mask: vk::ColorComponentFlags::all(),
+        };
+        // Without dual-source blending, the destination is first multiplied by the transmittance,
+        // and the sky pass proper only adds inscattered light.
+        let mut transmittance_blend = vec![untouched; color_attachment_count];
+        transmittance_blend[0] = vk::PipelineColorBlendAttachmentState {
+            src_color_blend_factor: vk::BlendFactor::ZERO,
+            dst_color_blend_factor: vk::BlendFactor::SRC_COLOR,
+            ..sky_blend[0]
+        };
+        // The background is added to whatever's behind the atmosphere, then attenuated by the sky
+        // pass along with it.
+        let mut background_blend = vec![untouched; color_attachment_count];
+        background_blend[0] = vk::PipelineColorBlendAttachmentState {
+            dst_color_blend_factor: vk::BlendFactor::ONE,
+            ..sky_blend[0]
+        };
+        let sky_blend_state =
+            vk::PipelineColorBlendStateCreateInfo::builder().attachments(&sky_blend);
+        let transmittance_blend_state =
+            vk::PipelineColorBlendStateCreateInfo::builder().attachments(&transmittance_blend);
+        let background_blend_state =
+            vk::PipelineColorBlendStateCreateInfo::builder().attachments(&background_blend);
+
+        let (render_pass, subpass, rendering) = match target {
+            Target::Subpass {
+                render_pass,
+                subpass,
+            } => (render_pass, subpass, None),
+            Target::Dynamic {
+                color_formats,
+                depth_format,
+            } => (
+                vk::RenderPass::null(),
+                0,
+                Some(PipelineRenderingCreateInfoKHR {
+                    s_type: STRUCTURE_TYPE_PIPELINE_RENDERING_CREATE_INFO_KHR,
+                    p_next: ptr::null(),
+                    view_mask,
+                    color_attachment_count: color_formats.len() as u32,
+                    p_color_attachment_formats: color_formats.as_ptr(),
+                    depth_attachment_format: match depth_format {
+                        vk::Format::S8_UINT => vk::Format::UNDEFINED,
+                        _ => depth_format,
+                    },
+                    stencil_attachment_format: stencil_format(depth_format),
+                }),
+            ),
+        };
+
+        let pipeline_info =
+            |stages: &[vk::PipelineShaderStageCreateInfo],
+             color_blend_state: &vk::PipelineColorBlendStateCreateInfo| {
+                let mut info = vk::GraphicsPipelineCreateInfo::builder()
+                    .stages(stages)
+                    .vertex_input_state(&vertex_input_state)
+                    .input_assembly_state(&input_assembly_state)
+                    .viewport_state(&viewport_state)
+                    .rasterization_state(&rasterization_state)
+                    .multisample_state(&multisample_state)
+                    .depth_stencil_state(&depth_stencil_state)
+                    .color_blend_state(color_blend_state)
+                    .dynamic_state(&dynamic_state)
+                    .layout(pipeline_layout)
+                    .render_pass(render_pass)
+                    .subpass(subpass)
+                    .build();
+                if let Some(ref rendering) = rendering {
+                    info.p_next = rendering as *const _ as *const c_void;
+                }
+                info
+            };
+        // In drawing order
+        let mut infos = vec![pipeline_info(&background_stages, &background_blend_state)];
+        if !dual_src_blend {
+            infos.push(pipeline_info(
+                &transmittance_stages,
+                &transmittance_blend_state,
+            ));
+        }
+        infos.push(pipeline_info(&sky_stages, &sky_blend_state));
+        let mut pipelines = device
+            .create_graphics_pipelines(cache, &infos, None)
+            .unwrap();
+
+        device.destroy_shader_module(vert, None);
+        device.destroy_shader_module(frag, None);
+        device.destroy_shader_module(background_frag, None);
+
+        let background_pipeline = pipelines.remove(0);
+        let sky_pipelines = pipelines;
+        builder.name(background_pipeline, "fuzzyblue background");
+        for &pipeline in &sky_pipelines {
+            builder.name(pipeline, "fuzzyblue sky");
+        }
+
+        let frame_pool = device
+            .create_descriptor_pool(
+                &vk::DescriptorPoolCreateInfo::builder()
+                    .max_sets(frames)
+                    .pool_sizes(&[
+                        vk::DescriptorPoolSize {
+                            ty: depth_descriptor_type,
+                            descriptor_count: frames,
+                        },
+                        vk::DescriptorPoolSize {
+                            ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                            descriptor_count: frames,
+                        },
+                        vk::DescriptorPoolSize {
+                            ty: vk::DescriptorType::UNIFORM_BUFFER,
+                            descriptor_count: frames,
+                        },
+                    ]),
+                None,
+            )
+            .unwrap();
+        let frame_count = frames;
+        let frames: Vec<Frame> = device
+            .allocate_descriptor_sets(
+                &vk::DescriptorSetAllocateInfo::builder()
+                    .descriptor_pool(frame_pool)
+                    .set_layouts(&(0..frames).map(|_| frame_ds_layout).collect::<Vec<_>>()),
+            )
+            .unwrap()
+            .into_iter()
+            .enumerate()
+            .map(|(i, ds)| {
+                builder.name(ds, &format!("fuzzyblue frame {}", i));
+                Frame {
+                    ds,
+                    background: false,
+                }
+            })
+            .collect();
+
+        let views = if multiview {
+            let view_count = 32 - view_mask.leading_zeros();
+            let buffer = device
+                .create_buffer(
+                    &vk::BufferCreateInfo {
+                        size: (VIEWS_SIZE * frame_count as usize) as vk::DeviceSize,
+                        usage: vk::BufferUsageFlags::UNIFORM_BUFFER,
+                        ..Default::default()
+                    },
                     None,
                 )
                 .unwrap();
+            let memory = {
+                let reqs = device.get_buffer_memory_requirements(buffer);
+                allocate(
+                    &device,
+                    builder.memory_props(),
+                    reqs,
+                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                )
+                .unwrap()
+            };
+            device.bind_buffer_memory(buffer, memory, 0).unwrap();
+            let mapped = device
+                .map_memory(memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty())
+                .unwrap() as *mut ViewRaw;
+            for (i, frame) in frames.iter().enumerate() {
+                device.update_descriptor_sets(
+                    &[vk::WriteDescriptorSet {
+                        dst_set: frame.ds,
+                        dst_binding: 2,
+                        dst_array_element: 0,
+                        descriptor_count: 1,
+                        descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                        p_buffer_info: &vk::DescriptorBufferInfo {
+                            buffer,
+                            offset: (i * VIEWS_SIZE) as vk::DeviceSize,
+                            range: VIEWS_SIZE as vk::DeviceSize,
+                        },
+                        ..Default::default()
+                    }],
+                    &[],
+                );
+            }
+            Some(ViewBuffer {
+                buffer,
+                memory,
+                mapped,
+                count: view_count,
+            })
+        } else {
+            None
+        };
+
+        Self {
+            device,
+            frame_ds_layout,
+            depth_descriptor_type,
+            pipeline_layout,
+            sky_pipelines,
+            background_pipeline,
+            frame_pool,
+            frames,
+            views,
+            debug: builder.debug().cloned(),
+        }
+    }
+
+    /// Set the depth buffer that limits the extent of the atmosphere for `frame`
+    ///
+    /// For a renderer constructed with `new`, `image` is an input attachment of the subpass. For
+    /// `new_dynamic`, it's a sampled image in a layout such as `DEPTH_STENCIL_READ_ONLY_OPTIMAL`,
+    /// read at each fragment's framebuffer coordinates, so it must cover the render area.
+    pub unsafe fn set_depth_buffer(&mut self, frame: u32, image: &vk::DescriptorImageInfo) {
+        self.device.update_descriptor_sets(
+            &[vk::WriteDescriptorSet {
+                dst_set: self.frames[frame as usize].ds,
+                dst_binding: 0,
+                dst_array_element: 0,
+                descriptor_count: 1,
+                descriptor_type: self.depth_descriptor_type,
+                p_image_info: image,
+                ..Default::default()
+            }],
+            &[],
+        );
+    }
+
+    /// Set the radiance visible through the atmosphere, such as stars, for `frame`
+    ///
+    /// `image` must be a cube map in the celestial frame, where +Z points towards the north
+    /// celestial pole and +X towards the vernal equinox, which
+    /// `DrawParameters::background_rotation` relates to the planet's frame. Texels are radiance, in
+    /// the same units as `DrawParameters::solar_irradiance` per steradian;
+    /// `stars::rasterize_cubemap` produces suitable data. The background is only drawn where the
+    /// depth buffer is at the far plane. `None` disables the background.
+    pub unsafe fn set_background(&mut self, frame: u32, image: Option<&vk::DescriptorImageInfo>) {
+        let frame = &mut self.frames[frame as usize];
+        frame.background = image.is_some();
+        if let Some(image) = image {
+            self.device.update_descriptor_sets(
+                &[vk::WriteDescriptorSet {
+                    dst_set: frame.ds,
+                    dst_binding: 1,
+                    dst_array_element: 0,
+                    descriptor_count: 1,
+                    descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    p_image_info: image,
+                    ..Default::default()
+                }],
+                &[],
+            );
+        }
+    }
+
+    pub fn draw(
+        &self,
+        cmd: vk::CommandBuffer,
+        atmosphere: &Atmosphere,
+        frame: u32,
+        params: &DrawParameters,
+    ) {
+        self.draw_blended(cmd, atmosphere, atmosphere, 0.0, frame, params);
+    }
+
+    /// Draw a crossfade from `from` to `to`, e.g. during a change in weather
+    ///
+    /// Radiance and transmittance are interpolated linearly by `blend`, from 0 for only `from` to
+    /// 1 for only `to`. Both atmospheres are sampled unless `blend` is 0.
+    pub fn draw_blended(
+        &self,
+        cmd: vk::CommandBuffer,
+        from: &Atmosphere,
+        to: &Atmosphere,
+        blend: f32,
+        frame: u32,
+        params: &DrawParameters,
+    ) {
+        assert!(
+            self.views.is_none(),
+            "multiview renderers must be drawn with draw_multiview or draw_multiview_blended"
+        );
+        self.record(cmd, from, to, blend, frame, params);
+    }
+
+    /// Draw with `VK_KHR_multiview`, using `views[i]` for the view with index `i`
+    ///
+    /// `params.inverse_viewproj` and `params.camera_position` are ignored. `views` must have an
+    /// element for each view up to the highest bit set in the view mask. `views` is written to
+    /// host-visible memory immediately, so `frame` must not be in use by the device.
+    pub fn draw_multiview(
+        &self,
+        cmd: vk::CommandBuffer,
+        atmosphere: &Atmosphere,
+        frame: u32,
+        params: &DrawParameters,
+        views: &[ViewParameters],
+    ) {
+        self.draw_multiview_blended(cmd, atmosphere, atmosphere, 0.0, frame, params, views);
+    }
+
+    /// Draw a crossfade from `from` to `to` with `VK_KHR_multiview`
+    ///
+    /// Combines `draw_blended` and `draw_multiview`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_multiview_blended(
+        &self,
+        cmd: vk::CommandBuffer,
+        from: &Atmosphere,
+        to: &Atmosphere,
+        blend: f32,
+        frame: u32,
+        params: &DrawParameters,
+        views: &[ViewParameters],
+    ) {
+        let buffer = self
+            .views
+            .as_ref()
+            .expect("renderer was constructed without multiview");
+        assert_eq!(
+            views.len(),
+            buffer.count as usize,
+            "wrong number of views for view mask"
+        );
+        assert!((frame as usize) < self.frames.len(), "frame out of range");
+        unsafe {
+            let dst = buffer.mapped.add(frame as usize * MAX_VIEWS);
+            for (i, view) in views.iter().enumerate() {
+                dst.add(i).write(ViewRaw::new(view));
+            }
+        }
+        self.record(cmd, from, to, blend, frame, params);
+    }
+
+    fn record(
+        &self,
+        cmd: vk::CommandBuffer,
+        from: &Atmosphere,
+        to: &Atmosphere,
+        blend: f32,
+        frame: u32,
+        params: &DrawParameters,
+    ) {
+        let frame = &self.frames[frame as usize];
+        unsafe {
+            if let Some(ref debug) = self.debug {
+                debug.begin_label(cmd, "fuzzyblue sky");
+            }
+            self.device.cmd_bind_descriptor_sets(
+                cmd,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline_layout,
+                0,
+                &[from.descriptor_set(), frame.ds, to.descriptor_set()],
+                &[],
+            );
+            self.device.cmd_push_constants(
+                cmd,
+                self.pipeline_layout,
+                vk::ShaderStageFlags::FRAGMENT,
+                0,
+                &mem::transmute::<_, [u8; 112]>(DrawParamsRaw::new(params, blend)),
+            );
+            if frame.background {
+                self.device.cmd_bind_pipeline(
+                    cmd,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    self.background_pipeline,
+                );
+                self.device.cmd_draw(cmd, 3, 1, 0, 0);
+            }
+            for &pipeline in &self.sky_pipelines {
+                self.device
+                    .cmd_bind_pipeline(cmd, vk::PipelineBindPoint::GRAPHICS, pipeline);
+                self.device.cmd_draw(cmd, 3, 1, 0, 0);
+            }
+            if let Some(ref debug) = self.debug {
+                debug.end_label(cmd);
+            }
+        }
+    }
+}
+
+/// Renders the atmosphere with compute shaders, modifying an image in place
+///
+/// An alternative to `Renderer` for pipelines that don't use rasterization for post-processing.
+pub struct ComputeRenderer {
+    device: Arc<Device>,
+    frame_ds_layout: vk::DescriptorSetLayout,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    background_pipeline: vk::Pipeline,
+    frame_pool: vk::DescriptorPool,
+    frames: Vec<Frame>,
+    debug: Option<DebugUtils>,
+}
+
+impl Drop for ComputeRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_descriptor_pool(self.frame_pool, None);
+            self.device.destroy_pipeline(self.pipeline, None);
+            self.device.destroy_pipeline(self.background_pipeline, None);
+            self.device
+                .destroy_pipeline_layout(self.pipeline_layout, None);
+            self.device
+                .destroy_descriptor_set_layout(self.frame_ds_layout, None);
+        }
+    }
+}
+
+impl ComputeRenderer {
+    /// Construct a compute atmosphere renderer
+    pub fn new(builder: &Builder, cache: vk::PipelineCache, frames: u32) -> Self {
+        Self::with_shaders(builder, cache, frames, ComputeRenderShaders::default())
+    }
 
-            let frag = device
-                .create_shader_module(
-                    &vk::ShaderModuleCreateInfo::builder().code(&RENDER_SKY),
+    /// Construct a compute renderer that dispatches `shaders` in place of the built-in shaders
+    pub fn with_shaders(
+        builder: &Builder,
+        cache: vk::PipelineCache,
+        frames: u32,
+        shaders: ComputeRenderShaders,
+    ) -> Self {
+        let device = builder.device().clone();
+        unsafe {
+            let sampler = builder.sampler();
+            let frame_bindings = compute_frame_bindings(&sampler);
+            let frame_ds_layout = device
+                .create_descriptor_set_layout(
+                    &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&frame_bindings),
                     None,
                 )
                 .unwrap();
@@ -57,9 +854,13 @@ impl Renderer {
             let pipeline_layout = device
                 .create_pipeline_layout(
                     &vk::PipelineLayoutCreateInfo::builder()
-                        .set_layouts(&[builder.render_ds_layout(), builder.frame_ds_layout()])
+                        .set_layouts(&[
+                            builder.render_ds_layout(),
+                            frame_ds_layout,
+                            builder.render_ds_layout(),
+                        ])
                         .push_constant_ranges(&[vk::PushConstantRange {
-                            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                            stage_flags: vk::ShaderStageFlags::COMPUTE,
                             offset: 0,
                             size: mem::size_of::<DrawParamsRaw>() as u32,
                         }]),
@@ -67,102 +868,79 @@ impl Renderer {
                 )
                 .unwrap();
 
-            let entry_point = b"main\0".as_ptr() as *const i8;
-            let noop_stencil_state = vk::StencilOpState {
-                fail_op: vk::StencilOp::KEEP,
-                pass_op: vk::StencilOp::KEEP,
-                depth_fail_op: vk::StencilOp::KEEP,
-                compare_op: vk::CompareOp::ALWAYS,
-                compare_mask: 0,
-                write_mask: 0,
-                reference: 0,
+            let render_bindings = precompute::render_bindings(&sampler);
+            let sets: [&[vk::DescriptorSetLayoutBinding]; 3] =
+                [&render_bindings, &frame_bindings, &render_bindings];
+            let interface = |name| Interface {
+                name,
+                stage: vk::ShaderStageFlags::COMPUTE,
+                sets: &sets,
+                push_constant_size: mem::size_of::<DrawParamsRaw>() as u32,
+                local_size: Some([WORKGROUP_SIZE, WORKGROUP_SIZE, 1]),
+                specialization: &[],
             };
+            let shader =
+                shader::create_module(&device, RENDER_SKY_COMPUTE, shaders.sky, &interface("sky"));
+            let background_shader = shader::create_module(
+                &device,
+                RENDER_SKY_COMPUTE_BACKGROUND,
+                shaders.background,
+                &interface("background"),
+            );
+
+            let p_name = b"main\0".as_ptr() as *const i8;
             let mut pipelines = device
-                .create_graphics_pipelines(
+                .create_compute_pipelines(
                     cache,
-                    &[vk::GraphicsPipelineCreateInfo::builder()
-                        .stages(&[
-                            vk::PipelineShaderStageCreateInfo {
-                                stage: vk::ShaderStageFlags::VERTEX,
-                                module: vert,
-                                p_name: entry_point,
+                    &[
+                        vk::ComputePipelineCreateInfo {
+                            stage: vk::PipelineShaderStageCreateInfo {
+                                stage: vk::ShaderStageFlags::COMPUTE,
+                                module: shader,
+                                p_name,
                                 ..Default::default()
                             },
-                            vk::PipelineShaderStageCreateInfo {
-                                stage: vk::ShaderStageFlags::FRAGMENT,
-                                module: frag,
-                                p_name: entry_point,
+                            layout: pipeline_layout,
+                            ..Default::default()
+                        },
+                        vk::ComputePipelineCreateInfo {
+                            stage: vk::PipelineShaderStageCreateInfo {
+                                stage: vk::ShaderStageFlags::COMPUTE,
+                                module: background_shader,
+                                p_name,
                                 ..Default::default()
                             },
-                        ])
-                        .vertex_input_state(&Default::default())
-                        .input_assembly_state(
-                            &vk::PipelineInputAssemblyStateCreateInfo::builder()
-                                .topology(vk::PrimitiveTopology::TRIANGLE_LIST),
-                        )
-                        .viewport_state(
-                            &vk::PipelineViewportStateCreateInfo::builder()
-                                .scissor_count(1)
-                                .viewport_count(1),
-                        )
-                        .rasterization_state(
-                            &vk::PipelineRasterizationStateCreateInfo::builder()
-                                .cull_mode(vk::CullModeFlags::NONE)
-                                .polygon_mode(vk::PolygonMode::FILL)
-                                .line_width(1.0),
-                        )
-                        .multisample_state(
-                            &vk::PipelineMultisampleStateCreateInfo::builder()
-                                .rasterization_samples(vk::SampleCountFlags::TYPE_1),
-                        )
-                        .depth_stencil_state(
-                            &vk::PipelineDepthStencilStateCreateInfo::builder()
-                                .depth_test_enable(false)
-                                .front(noop_stencil_state)
-                                .back(noop_stencil_state),
-                        )
-                        .color_blend_state(
-                            &vk::PipelineColorBlendStateCreateInfo::builder().attachments(&[
-                                vk::PipelineColorBlendAttachmentState {
-                                    blend_enable: vk::TRUE,
-                                    src_color_blend_factor: vk::BlendFactor::ONE,
-                                    dst_color_blend_factor: vk::BlendFactor::SRC1_COLOR,
-                                    color_blend_op: vk::BlendOp::ADD,
-                                    src_alpha_blend_factor: vk::BlendFactor::ZERO,
-                                    dst_alpha_blend_factor: vk::BlendFactor::ONE,
-                                    alpha_blend_op: vk::BlendOp::ADD,
-                                    color_write_mask: vk::ColorComponentFlags::all(),
-                                },
-                            ]),
-                        )
-                        .dynamic_state(
-                            &vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&[
-                                vk::DynamicState::VIEWPORT,
-                                vk::DynamicState::SCISSOR,
-                            ]),
-                        )
-                        .layout(pipeline_layout)
-                        .render_pass(render_pass)
-                        .subpass(subpass)
-                        .build()],
+                            layout: pipeline_layout,
+                            ..Default::default()
+                        },
+                    ],
                     None,
                 )
                 .unwrap()
                 .into_iter();
 
-            device.destroy_shader_module(vert, None);
-            device.destroy_shader_module(frag, None);
+            device.destroy_shader_module(shader, None);
+            device.destroy_shader_module(background_shader, None);
 
             let pipeline = pipelines.next().unwrap();
+            let background_pipeline = pipelines.next().unwrap();
+            builder.name(pipeline, "fuzzyblue sky");
+            builder.name(background_pipeline, "fuzzyblue background");
 
             let frame_pool = device
                 .create_descriptor_pool(
                     &vk::DescriptorPoolCreateInfo::builder()
                         .max_sets(frames)
-                        .pool_sizes(&[vk::DescriptorPoolSize {
-                            ty: vk::DescriptorType::INPUT_ATTACHMENT,
-                            descriptor_count: frames,
-                        }]),
+                        .pool_sizes(&[
+                            vk::DescriptorPoolSize {
+                                ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                                descriptor_count: 2 * frames,
+                            },
+                            vk::DescriptorPoolSize {
+                                ty: vk::DescriptorType::STORAGE_IMAGE,
+                                descriptor_count: frames,
+                            },
+                        ]),
                     None,
                 )
                 .unwrap();
@@ -170,27 +948,36 @@ impl Renderer {
                 .allocate_descriptor_sets(
                     &vk::DescriptorSetAllocateInfo::builder()
                         .descriptor_pool(frame_pool)
-                        .set_layouts(
-                            &(0..frames)
-                                .map(|_| builder.frame_ds_layout())
-                                .collect::<Vec<_>>(),
-                        ),
+                        .set_layouts(&(0..frames).map(|_| frame_ds_layout).collect::<Vec<_>>()),
                 )
                 .unwrap()
                 .into_iter()
-                .map(|ds| Frame { ds })
+                .enumerate()
+                .map(|(i, ds)| {
+                    builder.name(ds, &format!("fuzzyblue frame {}", i));
+                    Frame {
+                        ds,
+                        background: false,
+                    }
+                })
                 .collect();
 
             Self {
                 device,
+                frame_ds_layout,
                 pipeline_layout,
                 pipeline,
+                background_pipeline,
                 frame_pool,
                 frames,
+                debug: builder.debug().cloned(),
             }
         }
     }
 
+    /// Set the depth buffer that limits the extent of the atmosphere for `frame`
+    ///
+    /// `image` is a sampled image the same size as the color image, read without filtering.
     pub unsafe fn set_depth_buffer(&mut self, frame: u32, image: &vk::DescriptorImageInfo) {
         self.device.update_descriptor_sets(
             &[vk::WriteDescriptorSet {
@@ -198,7 +985,7 @@ impl Renderer {
                 dst_binding: 0,
                 dst_array_element: 0,
                 descriptor_count: 1,
-                descriptor_type: vk::DescriptorType::INPUT_ATTACHMENT,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
                 p_image_info: image,
                 ..Default::default()
             }],
@@ -206,38 +993,295 @@ impl Renderer {
         );
     }
 
-    pub fn draw(
+    /// Set the image that the atmosphere is rendered into for `frame`
+    ///
+    /// `image` must be an `R16G16B16A16_SFLOAT` storage image in the `GENERAL` layout, containing
+    /// the scene's radiance. Each pixel is replaced with the radiance reaching the camera through
+    /// the atmosphere. Alpha is preserved.
+    pub unsafe fn set_color_buffer(&mut self, frame: u32, image: &vk::DescriptorImageInfo) {
+        self.device.update_descriptor_sets(
+            &[vk::WriteDescriptorSet {
+                dst_set: self.frames[frame as usize].ds,
+                dst_binding: 2,
+                dst_array_element: 0,
+                descriptor_count: 1,
+                descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                p_image_info: image,
+                ..Default::default()
+            }],
+            &[],
+        );
+    }
+
+    /// Set the radiance visible through the atmosphere for `frame`
+    ///
+    /// See `Renderer::set_background`.
+    pub unsafe fn set_background(&mut self, frame: u32, image: Option<&vk::DescriptorImageInfo>) {
+        let frame = &mut self.frames[frame as usize];
+        frame.background = image.is_some();
+        if let Some(image) = image {
+            self.device.update_descriptor_sets(
+                &[vk::WriteDescriptorSet {
+                    dst_set: frame.ds,
+                    dst_binding: 1,
+                    dst_array_element: 0,
+                    descriptor_count: 1,
+                    descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    p_image_info: image,
+                    ..Default::default()
+                }],
+                &[],
+            );
+        }
+    }
+
+    /// Record a dispatch rendering the atmosphere into `frame`'s color buffer of size `extent`
+    ///
+    /// The caller is responsible for synchronizing access to the depth and color images with
+    /// `COMPUTE_SHADER` stage barriers.
+    pub fn dispatch(
         &self,
         cmd: vk::CommandBuffer,
         atmosphere: &Atmosphere,
         frame: u32,
         params: &DrawParameters,
+        extent: vk::Extent2D,
+    ) {
+        self.dispatch_blended(cmd, atmosphere, atmosphere, 0.0, frame, params, extent);
+    }
+
+    /// Record a dispatch rendering a crossfade from `from` to `to`, as in `Renderer::draw_blended`
+    #[allow(clippy::too_many_arguments)]
+    pub fn dispatch_blended(
+        &self,
+        cmd: vk::CommandBuffer,
+        from: &Atmosphere,
+        to: &Atmosphere,
+        blend: f32,
+        frame: u32,
+        params: &DrawParameters,
+        extent: vk::Extent2D,
     ) {
+        let frame = &self.frames[frame as usize];
         unsafe {
-            self.device
-                .cmd_bind_pipeline(cmd, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
+            if let Some(ref debug) = self.debug {
+                debug.begin_label(cmd, "fuzzyblue sky");
+            }
             self.device.cmd_bind_descriptor_sets(
                 cmd,
-                vk::PipelineBindPoint::GRAPHICS,
+                vk::PipelineBindPoint::COMPUTE,
                 self.pipeline_layout,
                 0,
-                &[atmosphere.descriptor_set(), self.frames[frame as usize].ds],
+                &[from.descriptor_set(), frame.ds, to.descriptor_set()],
                 &[],
             );
             self.device.cmd_push_constants(
                 cmd,
                 self.pipeline_layout,
-                vk::ShaderStageFlags::FRAGMENT,
+                vk::ShaderStageFlags::COMPUTE,
                 0,
-                &mem::transmute::<_, [u8; 92]>(DrawParamsRaw::new(params)),
+                &mem::transmute::<_, [u8; 112]>(DrawParamsRaw::new(params, blend)),
+            );
+            self.device.cmd_bind_pipeline(
+                cmd,
+                vk::PipelineBindPoint::COMPUTE,
+                if frame.background {
+                    self.background_pipeline
+                } else {
+                    self.pipeline
+                },
             );
-            self.device.cmd_draw(cmd, 3, 1, 0, 0);
+            self.device.cmd_dispatch(
+                cmd,
+                extent.width.div_ceil(WORKGROUP_SIZE),
+                extent.height.div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+            if let Some(ref debug) = self.debug {
+                debug.end_label(cmd);
+            }
         }
     }
 }
 
+/// Format of the stencil attachment that accompanies a depth attachment of `depth_format`
+fn stencil_format(depth_format: vk::Format) -> vk::Format {
+    match depth_format {
+        vk::Format::D16_UNORM_S8_UINT
+        | vk::Format::D24_UNORM_S8_UINT
+        | vk::Format::D32_SFLOAT_S8_UINT
+        | vk::Format::S8_UINT => depth_format,
+        _ => vk::Format::UNDEFINED,
+    }
+}
+
+fn frame_bindings(
+    depth_descriptor_type: vk::DescriptorType,
+    sampler: &vk::Sampler,
+) -> [vk::DescriptorSetLayoutBinding; 3] {
+    [
+        // depth
+        vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: depth_descriptor_type,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            p_immutable_samplers: ptr::null(),
+        },
+        // background
+        vk::DescriptorSetLayoutBinding {
+            binding: 1,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            p_immutable_samplers: sampler,
+        },
+        // views
+        vk::DescriptorSetLayoutBinding {
+            binding: 2,
+            descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            p_immutable_samplers: ptr::null(),
+        },
+    ]
+}
+
+fn compute_frame_bindings(sampler: &vk::Sampler) -> [vk::DescriptorSetLayoutBinding; 3] {
+    [
+        // depth
+        vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: ptr::null(),
+        },
+        // background
+        vk::DescriptorSetLayoutBinding {
+            binding: 1,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: sampler,
+        },
+        // color
+        vk::DescriptorSetLayoutBinding {
+            binding: 2,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: ptr::null(),
+        },
+    ]
+}
+
+/// Must match `local_size` in `render_sky.comp`
+const WORKGROUP_SIZE: u32 = 8;
+
 struct Frame {
     ds: vk::DescriptorSet,
+    background: bool,
+}
+
+struct ViewBuffer {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    /// Persistently mapped, `MAX_VIEWS` elements per frame
+    mapped: *mut ViewRaw,
+    count: u32,
+}
+
+// The mapping is only accessed through `Renderer::draw_multiview`, which the caller must not invoke
+// concurrently for the same frame.
+unsafe impl Send for ViewBuffer {}
+unsafe impl Sync for ViewBuffer {}
+
+/// Must match `MAX_VIEWS` in `draw_params.h`
+const MAX_VIEWS: usize = 32;
+/// Size of each frame's view parameters, a multiple of the largest possible
+/// `minUniformBufferOffsetAlignment`
+const VIEWS_SIZE: usize = MAX_VIEWS * mem::size_of::<ViewRaw>();
+
+#[derive(Copy, Clone)]
+enum Target<'a> {
+    Subpass {
+        render_pass: vk::RenderPass,
+        subpass: u32,
+    },
+    Dynamic {
+        color_formats: &'a [vk::Format],
+        depth_format: vk::Format,
+    },
+}
+
+/// `VkPipelineRenderingCreateInfoKHR`, from `VK_KHR_dynamic_rendering`
+#[repr(C)]
+struct PipelineRenderingCreateInfoKHR {
+    s_type: vk::StructureType,
+    p_next: *const c_void,
+    view_mask: u32,
+    color_attachment_count: u32,
+    p_color_attachment_formats: *const vk::Format,
+    depth_attachment_format: vk::Format,
+    stencil_attachment_format: vk::Format,
+}
+
+const STRUCTURE_TYPE_PIPELINE_RENDERING_CREATE_INFO_KHR: vk::StructureType =
+    vk::StructureType::from_raw(1_000_044_002);
+
+/// Optional configuration of a `Renderer`
+#[derive(Debug, Copy, Clone)]
+pub struct RendererOptions<'a> {
+    /// Sample count of the attachments drawn to
+    pub samples: vk::SampleCountFlags,
+    /// View mask of the subpass or rendering instance when using `VK_KHR_multiview`, or 0
+    pub view_mask: u32,
+    /// Whether the `dualSrcBlend` feature is enabled on the device, letting the sky be drawn in a
+    /// single pass
+    pub dual_src_blend: bool,
+    /// Replacements for the built-in shaders
+    pub shaders: RenderShaders<'a>,
+}
+
+impl Default for RendererOptions<'_> {
+    fn default() -> Self {
+        Self {
+            samples: vk::SampleCountFlags::TYPE_1,
+            view_mask: 0,
+            dual_src_blend: true,
+            shaders: RenderShaders::default(),
+        }
+    }
+}
+
+/// Replacement SPIR-V for the shaders run by `Renderer`, or `None` to use the built-in one
+///
+/// Each replacement must have a `main` entry point and may only use descriptors and push constants
+/// that the shader it replaces has access to, declaring images with the same dimensionality and
+/// multisampling; `Renderer` panics otherwise. The built-in shaders have variants for depth access,
+/// multisampling, and multiview, so replacements must suit the renderer's configuration. Without
+/// dual-source blending, `sky` is drawn twice, with boolean specialization constant 0 set for the
+/// pass that outputs transmittance.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct RenderShaders<'a> {
+    /// Generates the full-screen triangle that the other shaders are run over
+    pub vertex: Option<&'a [u32]>,
+    /// Draws the atmosphere's inscattered light and transmittance over the scene
+    pub sky: Option<&'a [u32]>,
+    /// Draws frames with a background set by `Renderer::set_background`
+    pub background: Option<&'a [u32]>,
+}
+
+/// Replacement SPIR-V for the shaders run by `ComputeRenderer`, or `None` to use the built-in one
+///
+/// Replacements are constrained as in `RenderShaders`, and must additionally have the same
+/// workgroup size as the shader they replace.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct ComputeRenderShaders<'a> {
+    pub sky: Option<&'a [u32]>,
+    /// Run for frames with a background set by `ComputeRenderer::set_background`
+    pub background: Option<&'a [u32]>,
 }
 
 /// Rendering parameters for an individual frame
@@ -249,23 +1293,174 @@ pub struct DrawParameters {
     pub inverse_viewproj: [[f32; 4]; 4],
     pub camera_position: [f32; 3],
     pub sun_direction: [f32; 3],
+    /// The solar irradiance at the top of the atmosphere, scaling all light from the sky
+    ///
+    /// About `[1.474, 1.850, 1.91198]` for Earth. May change from frame to frame without
+    /// rebuilding the `Atmosphere`.
+    pub solar_irradiance: [f32; 3],
+    /// Angle in radians about +Z from the planet's frame to the background's celestial frame
+    ///
+    /// For Earth, `ephemeris::sidereal_angle` gives the value at a particular time.
+    pub background_rotation: f32,
+    /// How the depth buffer and `inverse_viewproj` map depth values to distances
+    pub depth_convention: DepthConvention,
 }
 
+/// Relationship between depth buffer values and distance from the camera
+///
+/// Either convention may be used with an infinite far plane. Pixels at the far plane depth are
+/// treated as unobstructed sky.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DepthConvention {
+    /// Depth increases from 0 at the near plane to 1 at the far plane
+    Standard,
+    /// Depth decreases from 1 at the near plane to 0 at the far plane, as is common for better
+    /// precision with floating-point depth buffers
+    Reversed,
+}
+
+impl DepthConvention {
+    fn far_depth(self) -> f32 {
+        match self {
+            DepthConvention::Standard => 1.0,
+            DepthConvention::Reversed => 0.0,
+        }
+    }
+}
+
+/// Per-view parameters for multiview rendering
+///
+/// See `DrawParameters` for details.
+#[derive(Debug, Copy, Clone)]
+pub struct ViewParameters {
+    /// (projection * view)^-1
+    pub inverse_viewproj: [[f32; 4]; 4],
+    pub camera_position: [f32; 3],
+}
+
+/// std140 layout of `View` in `draw_params.h`
 #[repr(C)]
-struct DrawParamsRaw {
+struct ViewRaw {
     inverse_viewproj: [[f32; 4]; 4],
     camera_position: [f32; 3],
     _padding: u32,
+}
+
+impl ViewRaw {
+    fn new(x: &ViewParameters) -> Self {
+        Self {
+            inverse_viewproj: x.inverse_viewproj,
+            camera_position: x.camera_position,
+            _padding: 0,
+        }
+    }
+}
+
+#[repr(C)]
+struct DrawParamsRaw {
+    inverse_viewproj: [[f32; 4]; 4],
+    camera_position: [f32; 3],
+    background_rotation: f32,
     sun_direction: [f32; 3],
+    far_depth: f32,
+    solar_irradiance: [f32; 3],
+    blend: f32,
 }
 
 impl DrawParamsRaw {
-    fn new(x: &DrawParameters) -> Self {
+    fn new(x: &DrawParameters, blend: f32) -> Self {
         Self {
             inverse_viewproj: x.inverse_viewproj,
             camera_position: x.camera_position,
-            _padding: 0,
+            background_rotation: x.background_rotation,
             sun_direction: x.sun_direction,
+            far_depth: x.depth_convention.far_depth(),
+            solar_irradiance: x.solar_irradiance,
+            blend,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate_variant(code: &[u32], variant: usize, name: &str, stage: vk::ShaderStageFlags) {
+        let depth_descriptor_type = if variant & VARIANT_SAMPLED_DEPTH != 0 {
+            vk::DescriptorType::COMBINED_IMAGE_SAMPLER
+        } else {
+            vk::DescriptorType::INPUT_ATTACHMENT
+        };
+        let sampler = vk::Sampler::null();
+        let frame_bindings = frame_bindings(depth_descriptor_type, &sampler);
+        let frame_bindings = &frame_bindings[..if variant & VARIANT_MULTIVIEW != 0 {
+            3
+        } else {
+            2
+        }];
+        let render_bindings = precompute::render_bindings(&sampler);
+        let sets: [&[vk::DescriptorSetLayoutBinding]; 3] =
+            [&render_bindings, frame_bindings, &render_bindings];
+        let push_constant_size = if stage == vk::ShaderStageFlags::VERTEX {
+            0
+        } else {
+            mem::size_of::<DrawParamsRaw>() as u32
+        };
+        shader::validate(
+            code,
+            code,
+            &Interface {
+                name,
+                stage,
+                sets: &sets,
+                push_constant_size,
+                local_size: None,
+                specialization: &[],
+            },
+        );
+    }
+
+    #[test]
+    fn builtin_shaders_match_interface() {
+        for (variant, &code) in RENDER_BACKGROUND.iter().enumerate() {
+            validate_variant(FULLSCREEN, variant, "vertex", vk::ShaderStageFlags::VERTEX);
+            validate_variant(code, variant, "background", vk::ShaderStageFlags::FRAGMENT);
+        }
+        for (variant, &code) in RENDER_SKY.iter().enumerate() {
+            validate_variant(code, variant, "sky", vk::ShaderStageFlags::FRAGMENT);
+        }
+    }
+
+    #[test]
+    fn lighting_header_compiles() {
+        // Compiling is the real test; a broken render_lighting.h fails the build
+        const LIGHTING: &[u32] = include_glsl!("shaders/render_lighting_test.frag");
+        assert_eq!(LIGHTING[0], 0x0723_0203);
+    }
+
+    #[test]
+    fn builtin_compute_shaders_match_interface() {
+        let sampler = vk::Sampler::null();
+        let frame_bindings = compute_frame_bindings(&sampler);
+        let render_bindings = precompute::render_bindings(&sampler);
+        let sets: [&[vk::DescriptorSetLayoutBinding]; 3] =
+            [&render_bindings, &frame_bindings, &render_bindings];
+        for &(name, code) in &[
+            ("sky", RENDER_SKY_COMPUTE),
+            ("background", RENDER_SKY_COMPUTE_BACKGROUND),
+        ] {
+            shader::validate(
+                code,
+                code,
+                &Interface {
+                    name,
+                    stage: vk::ShaderStageFlags::COMPUTE,
+                    sets: &sets,
+                    push_constant_size: mem::size_of::<DrawParamsRaw>() as u32,
+                    local_size: Some([WORKGROUP_SIZE, WORKGROUP_SIZE, 1]),
+                    specialization: &[],
+                },
+            );
         }
     }
 }
@@ -0,0 +1,633 @@
+use std::collections::HashMap;
+
+use ash::version::DeviceV1_0;
+use ash::{vk, Device};
+
+/// Resources available to a shader stage through the pipeline layout it's built with
+pub(crate) struct Interface<'a> {
+    /// Identifies the shader in error messages
+    pub(crate) name: &'a str,
+    pub(crate) stage: vk::ShaderStageFlags,
+    /// Bindings of each descriptor set, in set order
+    pub(crate) sets: &'a [&'a [vk::DescriptorSetLayoutBinding]],
+    /// Size of the push constant range visible to `stage`, starting at offset 0
+    pub(crate) push_constant_size: u32,
+    /// Workgroup size assumed by dispatches, if any
+    pub(crate) local_size: Option<[u32; 3]>,
+    /// Values of the `u32` specialization constants from ID 0 up that the pipeline is created with
+    pub(crate) specialization: &'a [u32],
+}
+
+/// Create a shader module from `custom` if supplied, or `builtin` otherwise
+///
+/// Panics if `custom` is not compatible with `interface`.
+pub(crate) unsafe fn create_module(
+    device: &Device,
+    builtin: &[u32],
+    custom: Option<&[u32]>,
+    interface: &Interface,
+) -> vk::ShaderModule {
+    let code = match custom {
+        Some(code) => {
+            validate(code, builtin, interface);
+            code
+        }
+        None => builtin,
+    };
+    device
+        .create_shader_module(&vk::ShaderModuleCreateInfo::builder().code(code), None)
+        .unwrap()
+}
+
+/// Panic unless `code` is a SPIR-V module whose `main` entry point only uses resources from
+/// `interface`
+///
+/// Every descriptor the module declares is checked, whether or not it's used. Images must also
+/// have the dimensionality, arrayness, multisampling, and, for storage images, format that
+/// `builtin` declares at the same binding, if any.
+pub(crate) fn validate(code: &[u32], builtin: &[u32], interface: &Interface) {
+    let name = interface.name;
+    let module = Module::parse(code, name);
+    let builtin = Module::parse(builtin, name);
+
+    let model = match interface.stage {
+        vk::ShaderStageFlags::VERTEX => EXECUTION_MODEL_VERTEX,
+        vk::ShaderStageFlags::FRAGMENT => EXECUTION_MODEL_FRAGMENT,
+        vk::ShaderStageFlags::COMPUTE => EXECUTION_MODEL_GL_COMPUTE,
+        _ => unreachable!(),
+    };
+    let entry_point = module
+        .entry_points
+        .iter()
+        .find(|&&(m, _, ref n)| m == model && n == "main")
+        .unwrap_or_else(|| {
+            panic!(
+                "{} shader has no {:?} entry point named \"main\"",
+                name, interface.stage
+            )
+        })
+        .1;
+
+    if let Some(expected) = interface.local_size {
+        let actual = module
+            .local_size(entry_point, interface.specialization)
+            .unwrap_or_else(|| {
+                panic!("{} shader doesn't declare a workgroup size", name);
+            });
+        assert_eq!(
+            actual, expected,
+            "{} shader's workgroup size must be {:?}",
+            name, expected
+        );
+    }
+
+    for &(ty, id, storage_class) in &module.variables {
+        if storage_class == STORAGE_CLASS_PUSH_CONSTANT {
+            let size = module.size(module.pointee(ty), None);
+            assert!(
+                size <= interface.push_constant_size,
+                "{} shader uses {} bytes of push constants, but only {} are available",
+                name,
+                size,
+                interface.push_constant_size
+            );
+            continue;
+        }
+        let (descriptor_type, count) = match module.descriptor(ty, storage_class, name) {
+            Some(x) => x,
+            None => continue,
+        };
+        let set = module.decoration(id, DECORATION_DESCRIPTOR_SET);
+        let binding = module.decoration(id, DECORATION_BINDING);
+        let (set, binding) = match (set, binding) {
+            (Some(set), Some(binding)) => (set, binding),
+            _ => panic!(
+                "{} shader declares a descriptor without a set and binding",
+                name
+            ),
+        };
+        let expected = interface
+            .sets
+            .get(set as usize)
+            .and_then(|bindings| bindings.iter().find(|x| x.binding == binding))
+            .filter(|x| x.stage_flags.contains(interface.stage))
+            .unwrap_or_else(|| {
+                panic!(
+                    "{} shader uses binding {} of set {}, which doesn't exist",
+                    name, binding, set
+                )
+            });
+        assert_eq!(
+            descriptor_type, expected.descriptor_type,
+            "{} shader uses binding {} of set {} as the wrong type of descriptor",
+            name, binding, set
+        );
+        assert!(
+            count <= expected.descriptor_count,
+            "{} shader uses {} descriptors at binding {} of set {}, but only {} exist",
+            name,
+            count,
+            binding,
+            set,
+            expected.descriptor_count
+        );
+        if let (Some(actual), Some(expected)) = (module.image(ty), builtin.image_at(set, binding)) {
+            assert!(
+                actual.dim == expected.dim
+                    && actual.arrayed == expected.arrayed
+                    && actual.multisampled == expected.multisampled,
+                "{} shader uses binding {} of set {} as the wrong shape of image",
+                name,
+                binding,
+                set
+            );
+            let storage = descriptor_type == vk::DescriptorType::STORAGE_IMAGE
+                || descriptor_type == vk::DescriptorType::STORAGE_TEXEL_BUFFER;
+            assert!(
+                !storage || actual.format == expected.format,
+                "{} shader uses binding {} of set {} as an image of the wrong format",
+                name,
+                binding,
+                set
+            );
+        }
+    }
+}
+
+/// Properties of an image type that the views bound to it must agree with
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct ImageType {
+    dim: u32,
+    arrayed: u32,
+    multisampled: u32,
+    /// Only constrains storage images
+    format: u32,
+}
+
+/// The parts of a SPIR-V module needed to check its interface
+struct Module<'a> {
+    /// Execution model, function ID, and name of each entry point
+    entry_points: Vec<(u32, u32, String)>,
+    /// Literal workgroup size of each entry point that declares one
+    local_sizes: HashMap<u32, [u32; 3]>,
+    /// Constant IDs of the workgroup size of each entry point that declares one that way
+    local_size_ids: HashMap<u32, [u32; 3]>,
+    /// First literal operand of each decoration, by target ID and decoration
+    decorations: HashMap<(u32, u32), u32>,
+    /// First literal operand of each member decoration, by structure ID, member, and decoration
+    member_decorations: HashMap<(u32, u32, u32), u32>,
+    /// Instructions declaring types and constants, by result ID
+    definitions: HashMap<u32, &'a [u32]>,
+    /// Result type, result ID, and storage class of each global variable
+    variables: Vec<(u32, u32, u32)>,
+}
+
+impl<'a> Module<'a> {
+    fn parse(code: &'a [u32], name: &str) -> Self {
+        assert!(
+            code.len() >= 5 && code[0] == MAGIC,
+            "{} shader is not SPIR-V",
+            name
+        );
+        let mut module = Self {
+            entry_points: Vec::new(),
+            local_sizes: HashMap::new(),
+            local_size_ids: HashMap::new(),
+            decorations: HashMap::new(),
+            member_decorations: HashMap::new(),
+            definitions: HashMap::new(),
+            variables: Vec::new(),
+        };
+        let mut rest = &code[5..];
+        while !rest.is_empty() {
+            let len = (rest[0] >> 16) as usize;
+            assert!(
+                len != 0 && len <= rest.len(),
+                "{} shader is malformed SPIR-V",
+                name
+            );
+            let inst = &rest[..len];
+            rest = &rest[len..];
+            let operand = |i: usize| {
+                *inst
+                    .get(i)
+                    .unwrap_or_else(|| panic!("{} shader is malformed SPIR-V", name))
+            };
+            match opcode(inst) {
+                OP_ENTRY_POINT => {
+                    let bytes = inst[3..]
+                        .iter()
+                        .flat_map(|word| word.to_le_bytes().to_vec())
+                        .take_while(|&b| b != 0)
+                        .collect::<Vec<u8>>();
+                    module.entry_points.push((
+                        operand(1),
+                        operand(2),
+                        String::from_utf8_lossy(&bytes).into_owned(),
+                    ));
+                }
+                OP_EXECUTION_MODE if operand(2) == EXECUTION_MODE_LOCAL_SIZE => {
+                    module
+                        .local_sizes
+                        .insert(operand(1), [operand(3), operand(4), operand(5)]);
+                }
+                OP_EXECUTION_MODE_ID if operand(2) == EXECUTION_MODE_LOCAL_SIZE_ID => {
+                    module
+                        .local_size_ids
+                        .insert(operand(1), [operand(3), operand(4), operand(5)]);
+                }
+                OP_DECORATE => {
+                    module
+                        .decorations
+                        .insert((operand(1), operand(2)), inst.get(3).copied().unwrap_or(0));
+                }
+                OP_MEMBER_DECORATE => {
+                    module.member_decorations.insert(
+                        (operand(1), operand(2), operand(3)),
+                        inst.get(4).copied().unwrap_or(0),
+                    );
+                }
+                OP_TYPE_BOOL..=OP_TYPE_POINTER => {
+                    module.definitions.insert(operand(1), inst);
+                }
+                OP_CONSTANT
+                | OP_CONSTANT_COMPOSITE
+                | OP_SPEC_CONSTANT
+                | OP_SPEC_CONSTANT_COMPOSITE => {
+                    module.definitions.insert(operand(2), inst);
+                }
+                OP_VARIABLE if operand(3) != STORAGE_CLASS_FUNCTION => {
+                    module.variables.push((operand(1), operand(2), operand(3)));
+                }
+                _ => {}
+            }
+        }
+        module
+    }
+
+    fn decoration(&self, id: u32, decoration: u32) -> Option<u32> {
+        self.decorations.get(&(id, decoration)).copied()
+    }
+
+    fn definition(&self, id: u32) -> &'a [u32] {
+        self.definitions[&id]
+    }
+
+    /// Scalar value of a constant
+    fn constant(&self, id: u32) -> u32 {
+        self.definition(id)[3]
+    }
+
+    /// Scalar value of a constant once `specialization` is applied
+    fn specialized_constant(&self, id: u32, specialization: &[u32]) -> u32 {
+        if opcode(self.definition(id)) == OP_SPEC_CONSTANT {
+            let value = self
+                .decoration(id, DECORATION_SPEC_ID)
+                .and_then(|spec_id| specialization.get(spec_id as usize));
+            if let Some(&value) = value {
+                return value;
+            }
+        }
+        self.constant(id)
+    }
+
+    fn pointee(&self, pointer: u32) -> u32 {
+        self.definition(pointer)[3]
+    }
+
+    /// Workgroup size of `entry_point` once `specialization` is applied
+    ///
+    /// The `WorkgroupSize` built-in overrides the entry point's execution mode.
+    fn local_size(&self, entry_point: u32, specialization: &[u32]) -> Option<[u32; 3]> {
+        let builtin = self.definitions.iter().find(|&(&id, _)| {
+            self.decoration(id, DECORATION_BUILT_IN) == Some(BUILT_IN_WORKGROUP_SIZE)
+        });
+        let ids = match builtin {
+            Some((_, inst)) => [inst[3], inst[4], inst[5]],
+            None => match self.local_size_ids.get(&entry_point) {
+                Some(&ids) => ids,
+                None => return self.local_sizes.get(&entry_point).copied(),
+            },
+        };
+        let mut size = [0; 3];
+        for (x, &id) in size.iter_mut().zip(&ids) {
+            *x = self.specialized_constant(id, specialization);
+        }
+        Some(size)
+    }
+
+    /// Descriptor type and count of a variable, if it's a descriptor
+    fn descriptor(
+        &self,
+        pointer: u32,
+        storage_class: u32,
+        name: &str,
+    ) -> Option<(vk::DescriptorType, u32)> {
+        match storage_class {
+            STORAGE_CLASS_UNIFORM_CONSTANT
+            | STORAGE_CLASS_UNIFORM
+            | STORAGE_CLASS_STORAGE_BUFFER => {}
+            _ => return None,
+        }
+        let mut ty = self.pointee(pointer);
+        let mut count = 1;
+        loop {
+            let inst = self.definition(ty);
+            match opcode(inst) {
+                OP_TYPE_ARRAY => {
+                    count *= self.constant(inst[3]);
+                    ty = inst[2];
+                }
+                OP_TYPE_RUNTIME_ARRAY => {
+                    panic!("{} shader uses an unsized array of descriptors", name)
+                }
+                _ => break,
+            }
+        }
+        let inst = self.definition(ty);
+        let descriptor_type = match storage_class {
+            STORAGE_CLASS_UNIFORM_CONSTANT => match opcode(inst) {
+                OP_TYPE_SAMPLER => vk::DescriptorType::SAMPLER,
+                OP_TYPE_SAMPLED_IMAGE => {
+                    if self.definition(inst[2])[3] == DIM_BUFFER {
+                        vk::DescriptorType::UNIFORM_TEXEL_BUFFER
+                    } else {
+                        vk::DescriptorType::COMBINED_IMAGE_SAMPLER
+                    }
+                }
+                OP_TYPE_IMAGE => match (inst[3], inst[7]) {
+                    (DIM_SUBPASS_DATA, _) => vk::DescriptorType::INPUT_ATTACHMENT,
+                    (DIM_BUFFER, 2) => vk::DescriptorType::STORAGE_TEXEL_BUFFER,
+                    (DIM_BUFFER, _) => vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+                    (_, 2) => vk::DescriptorType::STORAGE_IMAGE,
+                    (_, _) => vk::DescriptorType::SAMPLED_IMAGE,
+                },
+                _ => panic!("{} shader uses an unsupported type of descriptor", name),
+            },
+            STORAGE_CLASS_UNIFORM => {
+                if self.decoration(ty, DECORATION_BUFFER_BLOCK).is_some() {
+                    vk::DescriptorType::STORAGE_BUFFER
+                } else {
+                    vk::DescriptorType::UNIFORM_BUFFER
+                }
+            }
+            _ => vk::DescriptorType::STORAGE_BUFFER,
+        };
+        Some((descriptor_type, count))
+    }
+
+    /// Image type of a variable, if it's an image or array of images
+    fn image(&self, pointer: u32) -> Option<ImageType> {
+        let mut inst = self.definition(self.pointee(pointer));
+        while opcode(inst) == OP_TYPE_ARRAY || opcode(inst) == OP_TYPE_SAMPLED_IMAGE {
+            inst = self.definition(inst[2]);
+        }
+        if opcode(inst) != OP_TYPE_IMAGE {
+            return None;
+        }
+        Some(ImageType {
+            dim: inst[3],
+            arrayed: inst[5],
+            multisampled: inst[6],
+            format: inst[8],
+        })
+    }
+
+    /// Image type of the descriptor declared at `binding` of `set`, if it's an image
+    fn image_at(&self, set: u32, binding: u32) -> Option<ImageType> {
+        let &(ty, _, _) = self.variables.iter().find(|&&(_, id, _)| {
+            self.decoration(id, DECORATION_DESCRIPTOR_SET) == Some(set)
+                && self.decoration(id, DECORATION_BINDING) == Some(binding)
+        })?;
+        self.image(ty)
+    }
+
+    /// Size in bytes of a type with explicit layout
+    fn size(&self, ty: u32, matrix_stride: Option<u32>) -> u32 {
+        let inst = self.definition(ty);
+        match opcode(inst) {
+            OP_TYPE_BOOL => 4,
+            OP_TYPE_INT | OP_TYPE_FLOAT => inst[2] / 8,
+            OP_TYPE_VECTOR => self.size(inst[2], None) * inst[3],
+            OP_TYPE_MATRIX => inst[3] * matrix_stride.unwrap_or_else(|| self.size(inst[2], None)),
+            OP_TYPE_ARRAY => {
+                let stride = self
+                    .decoration(ty, DECORATION_ARRAY_STRIDE)
+                    .unwrap_or_else(|| self.size(inst[2], None));
+                stride * self.constant(inst[3])
+            }
+            OP_TYPE_STRUCT => (0..inst.len() as u32 - 2)
+                .map(|member| {
+                    let decoration = |x| self.member_decorations.get(&(ty, member, x)).copied();
+                    decoration(DECORATION_OFFSET).unwrap_or(0)
+                        + self.size(
+                            inst[2 + member as usize],
+                            decoration(DECORATION_MATRIX_STRIDE),
+                        )
+                })
+                .max()
+                .unwrap_or(0),
+            _ => 0,
+        }
+    }
+}
+
+fn opcode(inst: &[u32]) -> u32 {
+    inst[0] & 0xFFFF
+}
+
+const MAGIC: u32 = 0x0723_0203;
+
+const OP_ENTRY_POINT: u32 = 15;
+const OP_EXECUTION_MODE: u32 = 16;
+const OP_TYPE_BOOL: u32 = 20;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLER: u32 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_CONSTANT_COMPOSITE: u32 = 44;
+const OP_SPEC_CONSTANT: u32 = 50;
+const OP_SPEC_CONSTANT_COMPOSITE: u32 = 51;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+const OP_EXECUTION_MODE_ID: u32 = 331;
+
+const EXECUTION_MODEL_VERTEX: u32 = 0;
+const EXECUTION_MODEL_FRAGMENT: u32 = 4;
+const EXECUTION_MODEL_GL_COMPUTE: u32 = 5;
+
+const EXECUTION_MODE_LOCAL_SIZE: u32 = 17;
+const EXECUTION_MODE_LOCAL_SIZE_ID: u32 = 38;
+
+const STORAGE_CLASS_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_CLASS_UNIFORM: u32 = 2;
+const STORAGE_CLASS_FUNCTION: u32 = 7;
+const STORAGE_CLASS_PUSH_CONSTANT: u32 = 9;
+const STORAGE_CLASS_STORAGE_BUFFER: u32 = 12;
+
+const DECORATION_SPEC_ID: u32 = 1;
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+const BUILT_IN_WORKGROUP_SIZE: u32 = 25;
+
+const DIM_BUFFER: u32 = 5;
+const DIM_SUBPASS_DATA: u32 = 6;
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use vk_shader_macros::include_glsl;
+
+    use super::*;
+
+    const TRANSMITTANCE: &[u32] = include_glsl!("shaders/transmittance.comp");
+    const INDIRECT_IRRADIANCE: &[u32] = include_glsl!("shaders/indirect_irradiance.comp");
+
+    fn binding(
+        binding: u32,
+        descriptor_type: vk::DescriptorType,
+    ) -> vk::DescriptorSetLayoutBinding {
+        vk::DescriptorSetLayoutBinding {
+            binding,
+            descriptor_type,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            p_immutable_samplers: ptr::null(),
+        }
+    }
+
+    /// Validate the transmittance shader `code` against the interface it's built with, with the
+    /// descriptor set it writes to replaced by `table_bindings`
+    fn validate_transmittance(
+        code: &[u32],
+        table_bindings: &[vk::DescriptorSetLayoutBinding],
+        local_size: [u32; 3],
+    ) {
+        let params_bindings = [binding(0, vk::DescriptorType::UNIFORM_BUFFER)];
+        validate(
+            code,
+            TRANSMITTANCE,
+            &Interface {
+                name: "transmittance",
+                stage: vk::ShaderStageFlags::COMPUTE,
+                sets: &[&params_bindings, table_bindings],
+                push_constant_size: 0,
+                local_size: Some(local_size),
+                specialization: &[500, 8, 8, 1],
+            },
+        );
+    }
+
+    /// Copy of `code` with `f` applied to its first `OpTypeImage` instruction
+    fn patch_image_type(code: &[u32], f: impl FnOnce(&mut [u32])) -> Vec<u32> {
+        let mut code = code.to_vec();
+        let mut i = 5;
+        while opcode(&code[i..]) != OP_TYPE_IMAGE {
+            i += (code[i] >> 16) as usize;
+        }
+        let len = (code[i] >> 16) as usize;
+        f(&mut code[i..i + len]);
+        code
+    }
+
+    #[test]
+    fn matching_interface() {
+        validate_transmittance(
+            TRANSMITTANCE,
+            &[binding(0, vk::DescriptorType::STORAGE_IMAGE)],
+            [8, 8, 1],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "as the wrong type of descriptor")]
+    fn wrong_descriptor_type() {
+        validate_transmittance(
+            TRANSMITTANCE,
+            &[binding(0, vk::DescriptorType::COMBINED_IMAGE_SAMPLER)],
+            [8, 8, 1],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "which doesn't exist")]
+    fn missing_binding() {
+        validate_transmittance(TRANSMITTANCE, &[], [8, 8, 1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "workgroup size must be")]
+    fn wrong_workgroup_size() {
+        validate_transmittance(
+            TRANSMITTANCE,
+            &[binding(0, vk::DescriptorType::STORAGE_IMAGE)],
+            [16, 16, 1],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "as the wrong shape of image")]
+    fn wrong_image_dimensionality() {
+        // Dim2D to Dim3D
+        let code = patch_image_type(TRANSMITTANCE, |inst| inst[3] = 2);
+        validate_transmittance(
+            &code,
+            &[binding(0, vk::DescriptorType::STORAGE_IMAGE)],
+            [8, 8, 1],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "as an image of the wrong format")]
+    fn wrong_storage_format() {
+        // Rgba16f to Rgba32f
+        let code = patch_image_type(TRANSMITTANCE, |inst| inst[8] = 1);
+        validate_transmittance(
+            &code,
+            &[binding(0, vk::DescriptorType::STORAGE_IMAGE)],
+            [8, 8, 1],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "bytes of push constants, but only 0 are available")]
+    fn oversized_push_constants() {
+        let params_bindings = [binding(0, vk::DescriptorType::UNIFORM_BUFFER)];
+        let irradiance_bindings = [
+            binding(0, vk::DescriptorType::COMBINED_IMAGE_SAMPLER),
+            binding(1, vk::DescriptorType::COMBINED_IMAGE_SAMPLER),
+            binding(2, vk::DescriptorType::COMBINED_IMAGE_SAMPLER),
+            binding(3, vk::DescriptorType::STORAGE_IMAGE),
+            binding(4, vk::DescriptorType::STORAGE_IMAGE),
+            binding(5, vk::DescriptorType::STORAGE_IMAGE),
+        ];
+        validate(
+            INDIRECT_IRRADIANCE,
+            INDIRECT_IRRADIANCE,
+            &Interface {
+                name: "indirect irradiance",
+                stage: vk::ShaderStageFlags::COMPUTE,
+                sets: &[&params_bindings, &irradiance_bindings],
+                push_constant_size: 0,
+                local_size: Some([8, 8, 1]),
+                specialization: &[32, 8, 8, 1],
+            },
+        );
+    }
+}
@@ -0,0 +1,386 @@
+//! Star catalogue loading for use as a background behind the atmosphere
+//!
+//! Stars are positioned in the J2000 equatorial frame: +Z points towards the north celestial pole
+//! and +X towards the vernal equinox. This is the celestial frame expected by
+//! `Renderer::set_background`.
+
+use std::f64::consts::PI;
+use std::io::{self, Read};
+
+/// A point source of light in the celestial sphere
+#[derive(Debug, Copy, Clone)]
+pub struct Star {
+    /// J2000 right ascension, in radians
+    pub right_ascension: f32,
+    /// J2000 declination, in radians
+    pub declination: f32,
+    /// Apparent visual magnitude
+    pub magnitude: f32,
+    /// Approximate effective temperature in kelvin, used for color
+    pub temperature: f32,
+}
+
+impl Star {
+    /// Unit vector towards the star in the celestial frame
+    pub fn direction(&self) -> [f32; 3] {
+        let (sin_ra, cos_ra) = self.right_ascension.sin_cos();
+        let (sin_dec, cos_dec) = self.declination.sin_cos();
+        [cos_dec * cos_ra, cos_dec * sin_ra, sin_dec]
+    }
+}
+
+/// Read a catalogue in the binary format of the Yale Bright Star Catalogue, e.g. `BSC5`
+///
+/// Either byte order is accepted. B1950 catalogues are precessed to J2000. Proper motion is
+/// ignored.
+pub fn read_bsc5<R: Read>(mut reader: R) -> io::Result<Vec<Star>> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    if data.len() < HEADER_SIZE {
+        return Err(invalid_data("truncated header"));
+    }
+
+    let header = [Endian::Little, Endian::Big]
+        .iter()
+        .map(|&endian| Header::parse(&data, endian))
+        .find(|header| header.entry_size() == Some(header.entry_bytes))
+        .ok_or_else(|| invalid_data("unrecognized header"))?;
+
+    let count = header.star_count.unsigned_abs() as usize;
+    let entry_size = header.entry_bytes as usize;
+    if data.len() < HEADER_SIZE + count * entry_size {
+        return Err(invalid_data("truncated entries"));
+    }
+
+    let mut stars = Vec::with_capacity(count);
+    for entry in data[HEADER_SIZE..].chunks_exact(entry_size).take(count) {
+        let e = header.endian;
+        let mut offset = header.id_size();
+        let ra = e.f64(&entry[offset..]);
+        let dec = e.f64(&entry[offset + 8..]);
+        offset += 16;
+        let spectral_type = [entry[offset], entry[offset + 1]];
+        offset += 2;
+        let magnitude = f32::from(e.i16(&entry[offset..])) / 100.0;
+        if ra == 0.0 && dec == 0.0 {
+            // Placeholder for an entry that's been removed from the catalogue
+            continue;
+        }
+
+        let (ra, dec) = if header.j2000() {
+            (ra, dec)
+        } else {
+            precess_b1950(ra, dec)
+        };
+        stars.push(Star {
+            right_ascension: ra as f32,
+            declination: dec as f32,
+            magnitude,
+            temperature: temperature(spectral_type),
+        });
+    }
+    Ok(stars)
+}
+
+/// Render `stars` into the faces of a cube map for `Renderer::set_background`
+///
+/// Returns `6 * size * size` RGBA texels, in face order +X, -X, +Y, -Y, +Z, -Z as for Vulkan cube
+/// image layers, suitable for upload to an `R32G32B32A32_SFLOAT` image. Each star's light falls
+/// entirely within a single texel, so `size` should be large enough that stars appear as points at
+/// the intended resolution. `solar_irradiance` should match `DrawParameters::solar_irradiance`,
+/// which fixes the brightness of a star relative to the sun from its magnitude.
+pub fn rasterize_cubemap(stars: &[Star], size: u32, solar_irradiance: [f32; 3]) -> Vec<[f32; 4]> {
+    let size = size as usize;
+    let mut texels = vec![[0.0; 4]; 6 * size * size];
+    let texel_size = 2.0 / size as f64;
+    for star in stars {
+        let [x, y, z] = star.direction();
+        let (x, y, z) = (f64::from(x), f64::from(y), f64::from(z));
+        let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+        let (face, sc, tc, ma) = if ax >= ay && ax >= az {
+            if x > 0.0 {
+                (0, -z, -y, ax)
+            } else {
+                (1, z, -y, ax)
+            }
+        } else if ay >= az {
+            if y > 0.0 {
+                (2, x, z, ay)
+            } else {
+                (3, x, -z, ay)
+            }
+        } else if z > 0.0 {
+            (4, x, -y, az)
+        } else {
+            (5, -x, -y, az)
+        };
+        let texel = |c: f64| ((((c / ma) + 1.0) / texel_size) as usize).min(size - 1);
+        let (u, v) = (texel(sc), texel(tc));
+
+        // Solid angle subtended by the texel
+        let a = (u as f64 + 0.5) * texel_size - 1.0;
+        let b = (v as f64 + 0.5) * texel_size - 1.0;
+        let solid_angle = texel_size * texel_size / (1.0 + a * a + b * b).powf(1.5);
+
+        let brightness = 10f64.powf(-0.4 * (f64::from(star.magnitude) - SUN_MAGNITUDE));
+        let color = color(f64::from(star.temperature));
+        let out = &mut texels[(face * size + v) * size + u];
+        for ((out, &irradiance), &color) in out.iter_mut().zip(&solar_irradiance).zip(&color) {
+            *out += (f64::from(irradiance) * brightness * color / solid_angle) as f32;
+        }
+    }
+    texels
+}
+
+/// Color of a black body at `temperature` relative to the sun, normalized to unit green
+fn color(temperature: f64) -> [f64; 3] {
+    let relative =
+        |wavelength: f64| planck(wavelength, temperature) / planck(wavelength, SUN_TEMPERATURE);
+    let green = relative(WAVELENGTHS[1]);
+    [
+        relative(WAVELENGTHS[0]) / green,
+        1.0,
+        relative(WAVELENGTHS[2]) / green,
+    ]
+}
+
+/// Black body spectral radiance, up to a constant factor
+fn planck(wavelength: f64, temperature: f64) -> f64 {
+    // Second radiation constant hc/k, in nm K
+    const C2: f64 = 1.438_776_9e7;
+    1.0 / (wavelength.powi(5) * ((C2 / (wavelength * temperature)).exp() - 1.0))
+}
+
+/// Rough effective temperature from a Morgan-Keenan spectral type, e.g. `b"G2"`
+fn temperature(spectral_type: [u8; 2]) -> f32 {
+    const CLASSES: [(u8, f32); 8] = [
+        (b'O', 50_000.0),
+        (b'B', 30_000.0),
+        (b'A', 9_800.0),
+        (b'F', 7_300.0),
+        (b'G', 5_940.0),
+        (b'K', 5_150.0),
+        (b'M', 3_840.0),
+        (0, 2_300.0),
+    ];
+    let class = spectral_type[0].to_ascii_uppercase();
+    let i = match CLASSES.iter().position(|&(c, _)| c == class && c != 0) {
+        Some(i) => i,
+        None => return SUN_TEMPERATURE as f32,
+    };
+    let subclass = match spectral_type[1] {
+        x @ b'0'..=b'9' => f32::from(x - b'0') / 10.0,
+        _ => 0.0,
+    };
+    CLASSES[i].1 + (CLASSES[i + 1].1 - CLASSES[i].1) * subclass
+}
+
+/// Convert B1950 right ascension and declination to J2000
+fn precess_b1950(ra: f64, dec: f64) -> (f64, f64) {
+    const M: [[f64; 3]; 3] = [
+        [0.999_925_678_2, -0.011_182_061_1, -0.004_857_947_7],
+        [0.011_182_061_0, 0.999_937_478_4, -0.000_027_176_5],
+        [0.004_857_947_9, -0.000_027_147_4, 0.999_988_199_7],
+    ];
+    let v = [dec.cos() * ra.cos(), dec.cos() * ra.sin(), dec.sin()];
+    let r = [
+        M[0][0] * v[0] + M[0][1] * v[1] + M[0][2] * v[2],
+        M[1][0] * v[0] + M[1][1] * v[1] + M[1][2] * v[2],
+        M[2][0] * v[0] + M[2][1] * v[1] + M[2][2] * v[2],
+    ];
+    (r[1].atan2(r[0]).rem_euclid(2.0 * PI), r[2].asin())
+}
+
+struct Header {
+    endian: Endian,
+    /// Negative for J2000 coordinates
+    star_count: i32,
+    /// 0 for no ID, positive for a 4-byte catalogue number, negative for a name of that length
+    id_kind: i32,
+    /// 0 for none, 1 for proper motion, 2 for proper motion and radial velocity
+    motion_kind: i32,
+    /// Negative for J2000 coordinates
+    magnitude_count: i32,
+    entry_bytes: i32,
+}
+
+impl Header {
+    fn parse(data: &[u8], endian: Endian) -> Self {
+        let field = |i: usize| endian.i32(&data[4 * i..]);
+        Self {
+            endian,
+            star_count: field(2),
+            id_kind: field(3),
+            motion_kind: field(4),
+            magnitude_count: field(5),
+            entry_bytes: field(6),
+        }
+    }
+
+    fn id_size(&self) -> usize {
+        match self.id_kind {
+            0 => 0,
+            x if x > 0 => 4,
+            x => -x as usize,
+        }
+    }
+
+    /// Expected size of each entry, if the header is sensible
+    fn entry_size(&self) -> Option<i32> {
+        let magnitudes = self.magnitude_count.abs();
+        if !(1..=10).contains(&magnitudes) || self.id_kind.abs() > 64 {
+            return None;
+        }
+        let motion = match self.motion_kind {
+            0 => 0,
+            1 => 8,
+            2 => 16,
+            _ => return None,
+        };
+        Some(self.id_size() as i32 + 16 + 2 + 2 * magnitudes + motion)
+    }
+
+    fn j2000(&self) -> bool {
+        self.star_count < 0 || self.magnitude_count < 0
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    fn i16(self, x: &[u8]) -> i16 {
+        let x = [x[0], x[1]];
+        match self {
+            Endian::Little => i16::from_le_bytes(x),
+            Endian::Big => i16::from_be_bytes(x),
+        }
+    }
+
+    fn i32(self, x: &[u8]) -> i32 {
+        let x = [x[0], x[1], x[2], x[3]];
+        match self {
+            Endian::Little => i32::from_le_bytes(x),
+            Endian::Big => i32::from_be_bytes(x),
+        }
+    }
+
+    fn f64(self, x: &[u8]) -> f64 {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&x[..8]);
+        match self {
+            Endian::Little => f64::from_le_bytes(bytes),
+            Endian::Big => f64::from_be_bytes(bytes),
+        }
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+const HEADER_SIZE: usize = 28;
+/// Apparent visual magnitude of the sun
+const SUN_MAGNITUDE: f64 = -26.74;
+const SUN_TEMPERATURE: f64 = 5778.0;
+/// Representative wavelengths of the red, green, and blue channels, in nm
+const WAVELENGTHS: [f64; 3] = [680.0, 550.0, 440.0];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rigel's B1950 right ascension and declination, as in BSC5
+    const RIGEL_B1950: (f64, f64) = (
+        (5.0 + 12.0 / 60.0 + 8.0 / 3600.0) * 15.0 * PI / 180.0,
+        -(8.0 + 15.0 / 60.0 + 29.0 / 3600.0) * PI / 180.0,
+    );
+    /// Rigel's J2000 right ascension and declination
+    const RIGEL_J2000: (f64, f64) = (
+        (5.0 + 14.0 / 60.0 + 32.272 / 3600.0) * 15.0 * PI / 180.0,
+        -(8.0 + 12.0 / 60.0 + 5.9 / 3600.0) * PI / 180.0,
+    );
+
+    /// A B1950 catalogue in the layout of BSC5 holding Rigel and a removed entry
+    fn catalogue(big_endian: bool) -> Vec<u8> {
+        let mut data = Vec::new();
+        let i32 = |data: &mut Vec<u8>, x: i32| {
+            data.extend_from_slice(&if big_endian {
+                x.to_be_bytes()
+            } else {
+                x.to_le_bytes()
+            })
+        };
+        let f64 = |data: &mut Vec<u8>, x: f64| {
+            data.extend_from_slice(&if big_endian {
+                x.to_be_bytes()
+            } else {
+                x.to_le_bytes()
+            })
+        };
+        for &x in &[0, 1, 2, 1, 1, 1, 32] {
+            i32(&mut data, x);
+        }
+        for &(id, (ra, dec)) in &[(1713, RIGEL_B1950), (1714, (0.0, 0.0))] {
+            i32(&mut data, id);
+            f64(&mut data, ra);
+            f64(&mut data, dec);
+            data.extend_from_slice(b"B8");
+            let magnitude = 13i16;
+            data.extend_from_slice(&if big_endian {
+                magnitude.to_be_bytes()
+            } else {
+                magnitude.to_le_bytes()
+            });
+            // Proper motion
+            data.extend_from_slice(&[0; 8]);
+        }
+        data
+    }
+
+    #[test]
+    fn read_both_byte_orders() {
+        for &big_endian in &[false, true] {
+            let stars = read_bsc5(&catalogue(big_endian)[..]).unwrap();
+            assert_eq!(stars.len(), 1);
+            assert!((stars[0].magnitude - 0.13).abs() < 1e-6);
+            assert!((stars[0].temperature - 13_840.0).abs() < 1.0);
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        let data = catalogue(false);
+        assert!(read_bsc5(&data[..data.len() - 1]).is_err());
+        assert!(read_bsc5(&data[..HEADER_SIZE - 1]).is_err());
+    }
+
+    #[test]
+    fn precession() {
+        let stars = read_bsc5(&catalogue(false)[..]).unwrap();
+        // Within 5 arcseconds, the precision of the B1950 coordinates
+        let tolerance = 5.0 / 3600.0 * PI / 180.0;
+        assert!((f64::from(stars[0].right_ascension) - RIGEL_J2000.0).abs() < tolerance);
+        assert!((f64::from(stars[0].declination) - RIGEL_J2000.1).abs() < tolerance);
+    }
+
+    #[test]
+    fn rasterize_onto_face() {
+        let stars = read_bsc5(&catalogue(false)[..]).unwrap();
+        let size = 4;
+        let texels = rasterize_cubemap(&stars, size, [1.0; 3]);
+        // Rigel lies just south of the celestial equator, nearest +Y
+        let lit = texels
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| x.iter().any(|&c| c > 0.0))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        let face = 2;
+        let (u, v) = (2, 1);
+        assert_eq!(lit, [(face * size as usize + v) * size as usize + u]);
+    }
+}
@@ -119,41 +119,58 @@ fn smoke() {
             pdevice,
             queue_family_index,
             None,
+            Default::default(),
         ));
 
-        device
-            .begin_command_buffer(
-                cmd,
-                &vk::CommandBufferBeginInfo::builder()
-                    .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
-            )
-            .unwrap();
-
-        let pending = fuzzyblue::Atmosphere::build(
-            builder,
-            cmd,
-            // Simplified for speed
-            &fuzzyblue::Parameters {
-                scattering_r_size: 8,
-                scattering_mu_size: 32,
-                scattering_mu_s_size: 8,
-                scattering_nu_size: 2,
-                ..Default::default()
-            },
-        );
-
-        device.end_command_buffer(cmd).unwrap();
-
-        device
-            .queue_submit(
-                queue,
-                &[vk::SubmitInfo::builder().command_buffers(&[cmd]).build()],
-                vk::Fence::null(),
-            )
-            .unwrap();
-
-        device.device_wait_idle().unwrap();
-
+        // Simplified for speed, with every optional precompute feature enabled
+        let mut params = fuzzyblue::Parameters {
+            scattering_r_size: 8,
+            scattering_mu_size: 32,
+            scattering_mu_s_size: 8,
+            scattering_nu_size: 2,
+            retain_single_scattering: true,
+            convergence_threshold: Some(1e-3),
+            validate_luts: true,
+            export_orders: true,
+            record_timings: true,
+            ..Default::default()
+        };
+
+        let begin = || {
+            device
+                .begin_command_buffer(
+                    cmd,
+                    &vk::CommandBufferBeginInfo::builder()
+                        .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+                )
+                .unwrap();
+        };
+        let submit = || {
+            device.end_command_buffer(cmd).unwrap();
+            device
+                .queue_submit(
+                    queue,
+                    &[vk::SubmitInfo::builder().command_buffers(&[cmd]).build()],
+                    vk::Fence::null(),
+                )
+                .unwrap();
+            device.device_wait_idle().unwrap();
+        };
+
+        begin();
+        let pending = fuzzyblue::Atmosphere::build(builder, cmd, &params);
+        submit();
+        assert!(pending.lut_report().unwrap().is_valid());
+        assert!(pending.timings().is_some());
+        let atmosphere = pending.assert_ready();
+
+        // Only `ground_albedo` changes, so the retained single scattering is reused
+        params.ground_albedo = [0.3, 0.3, 0.3];
+        begin();
+        let pending = atmosphere.rebuild(cmd, &params);
+        submit();
+        assert!(pending.lut_report().unwrap().is_valid());
+        assert!(pending.timings().is_some());
         drop(pending);
 
         if let Some(ref mut rd) = rd {